@@ -0,0 +1,116 @@
+use super::{gamelog, paths, raws};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Achievement names and when they were first unlocked. Read from disk the first time it's needed
+// and shared by every run from then on.
+static UNLOCKED: LazyLock<Mutex<BTreeMap<String, u64>>> =
+    LazyLock::new(|| Mutex::new(load_unlocked()));
+
+fn unlocked_file() -> PathBuf {
+    paths::data_dir().join("achievements.json")
+}
+
+fn load_unlocked() -> BTreeMap<String, u64> {
+    fs::read_to_string(unlocked_file())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_unlocked(_unlocked: &BTreeMap<String, u64>) {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_unlocked(unlocked: &BTreeMap<String, u64>) {
+    let data = serde_json::to_string_pretty(unlocked).expect("Unable to serialize achievements");
+    let result = fs::create_dir_all(paths::data_dir())
+        .and_then(|()| paths::write_atomically(&unlocked_file(), data.as_bytes()));
+    if let Err(e) = result {
+        rltk::console::log(format!("Unable to save achievements: {e}"));
+    }
+}
+
+// Compares the event counters against the achievements in the raws, announcing and saving any
+// that have just been reached. Only does any work when a counter has changed.
+pub fn check_unlocks() {
+    if !gamelog::take_events_changed() {
+        return;
+    }
+
+    let reached: Vec<_> = {
+        let unlocked = UNLOCKED.lock().unwrap();
+        raws::get_achievements()
+            .into_iter()
+            .filter(|achievement| {
+                !unlocked.contains_key(&achievement.name)
+                    && gamelog::get_event_count(&achievement.event) >= achievement.threshold
+            })
+            .collect()
+    };
+    if reached.is_empty() {
+        return;
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let unlocked = {
+        let mut unlocked = UNLOCKED.lock().unwrap();
+        for achievement in &reached {
+            unlocked.insert(achievement.name.clone(), now);
+        }
+        unlocked.clone()
+    };
+    save_unlocked(&unlocked);
+
+    for achievement in reached {
+        gamelog::Logger::new()
+            .color(rltk::GOLD)
+            .append(&"Achievement unlocked:")
+            .append(&achievement.name)
+            .color(rltk::WHITE)
+            .append(&format!("- {}", achievement.description))
+            .log();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_unlocked, unlocked_file, UNLOCKED};
+    use crate::command::Command;
+    use crate::gamelog;
+    use crate::headless::Simulation;
+
+    fn times_announced(name: &str) -> usize {
+        gamelog::log_lines(usize::MAX)
+            .iter()
+            .filter(|line| line.starts_with("Achievement unlocked:") && line.contains(name))
+            .count()
+    }
+
+    #[test]
+    fn an_achievement_unlocks_once_when_its_threshold_is_crossed() {
+        let _lock = crate::headless::test_lock();
+        UNLOCKED.lock().unwrap().clear();
+        let mut sim = Simulation::new(7);
+
+        sim.step(Command::Wait);
+        assert!(!UNLOCKED.lock().unwrap().contains_key("First Blood"));
+
+        gamelog::record_event(&"Kills", 1);
+        sim.step(Command::Wait);
+        assert_eq!(times_announced("First Blood"), 1);
+        assert!(unlocked_file().exists());
+        let saved = load_unlocked();
+        assert!(saved.contains_key("First Blood"));
+
+        gamelog::record_event(&"Kills", 1);
+        sim.step(Command::Wait);
+        assert_eq!(times_announced("First Blood"), 1);
+        assert_eq!(load_unlocked(), saved);
+    }
+}
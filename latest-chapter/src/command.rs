@@ -0,0 +1,416 @@
+use super::{
+    gamelog, player,
+    raws::{find_spell_entity, get_vendor_items, spawn_named_item, SpawnType, RAWS},
+    CursedItem, Equipped, IdentifiedItem, InBackpack, Item, KnownSpells, Map, MasterDungeonMap,
+    Name, ObfuscatedName, Pools, Ranged, RunState, SpellTemplate, Vendor, WantsToCastSpell,
+    WantsToDropItem, WantsToRemoveItem, WantsToUseItem,
+};
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::str::FromStr;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Cheat {
+    TeleportToExit,
+    Heal,
+    Reveal,
+    GodMode,
+}
+
+// Items and spells are referred to by their position in the list the matching menu shows, so
+// commands stay meaningful when written down and played back.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Command {
+    Move { dx: i32, dy: i32 },
+    Wait,
+    PickUp,
+    UseItem { slot: usize, target: Option<Point> },
+    Cast { slot: usize, target: Option<Point> },
+    DropItem { slot: usize },
+    RemoveItem { slot: usize },
+    RemoveCurse { slot: usize },
+    Identify { slot: usize },
+    CycleTarget,
+    Fire,
+    Descend,
+    Ascend,
+    Sell { slot: usize },
+    Buy { slot: usize },
+    Cheat { cheat: Cheat },
+    Reload,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<&str> = s.split_whitespace().collect();
+        let number = |i: usize| -> Result<i32, String> {
+            tokens
+                .get(i)
+                .ok_or_else(|| format!("Missing argument in [{s}]"))?
+                .parse::<i32>()
+                .map_err(|_| format!("Not a number in [{s}]"))
+        };
+        let target = |i: usize| -> Result<Option<Point>, String> {
+            if tokens.len() > i {
+                Ok(Some(Point::new(number(i)?, number(i + 1)?)))
+            } else {
+                Ok(None)
+            }
+        };
+
+        match tokens.first().copied() {
+            Some("move") => Ok(Self::Move {
+                dx: number(1)?.clamp(-1, 1),
+                dy: number(2)?.clamp(-1, 1),
+            }),
+            Some("wait") => Ok(Self::Wait),
+            Some("pickup") => Ok(Self::PickUp),
+            Some("use") => Ok(Self::UseItem {
+                slot: number(1)? as usize,
+                target: target(2)?,
+            }),
+            Some("cast") => Ok(Self::Cast {
+                slot: number(1)? as usize,
+                target: target(2)?,
+            }),
+            Some("drop") => Ok(Self::DropItem {
+                slot: number(1)? as usize,
+            }),
+            Some("remove") => Ok(Self::RemoveItem {
+                slot: number(1)? as usize,
+            }),
+            Some("removecurse") => Ok(Self::RemoveCurse {
+                slot: number(1)? as usize,
+            }),
+            Some("identify") => Ok(Self::Identify {
+                slot: number(1)? as usize,
+            }),
+            Some("cycle") => Ok(Self::CycleTarget),
+            Some("fire") => Ok(Self::Fire),
+            Some("descend") => Ok(Self::Descend),
+            Some("ascend") => Ok(Self::Ascend),
+            Some("sell") => Ok(Self::Sell {
+                slot: number(1)? as usize,
+            }),
+            Some("buy") => Ok(Self::Buy {
+                slot: number(1)? as usize,
+            }),
+            _ => Err(format!("Unknown command [{s}]")),
+        }
+    }
+}
+
+#[must_use]
+pub fn backpack_items(ecs: &World) -> Vec<Entity> {
+    let player_entity = *ecs.fetch::<Entity>();
+    (&ecs.entities(), &ecs.read_storage::<InBackpack>())
+        .join()
+        .filter(|(_, backpack)| backpack.owner == player_entity)
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+#[must_use]
+pub fn equipped_items(ecs: &World) -> Vec<Entity> {
+    let player_entity = *ecs.fetch::<Entity>();
+    (&ecs.entities(), &ecs.read_storage::<Equipped>())
+        .join()
+        .filter(|(_, equipped)| equipped.owner == player_entity)
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+#[must_use]
+pub fn backpack_slot(ecs: &World, item: Entity) -> Option<usize> {
+    backpack_items(ecs).iter().position(|e| *e == item)
+}
+
+// What the identify menu offers: carried or worn items whose kind hasn't been identified yet.
+#[must_use]
+pub fn identifiable_items(ecs: &World) -> Vec<Entity> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let names = ecs.read_storage::<Name>();
+    let obfuscated = ecs.read_storage::<ObfuscatedName>();
+    let dm = ecs.fetch::<MasterDungeonMap>();
+    (&ecs.entities(), &ecs.read_storage::<Item>())
+        .join()
+        .map(|(entity, _)| entity)
+        .filter(|item| {
+            let owned = backpack
+                .get(*item)
+                .is_some_and(|bp| bp.owner == player_entity)
+                || equipped
+                    .get(*item)
+                    .is_some_and(|eq| eq.owner == player_entity);
+            owned
+                && obfuscated.get(*item).is_some()
+                && names
+                    .get(*item)
+                    .is_some_and(|name| !dm.identified_items.contains(&name.name))
+        })
+        .collect()
+}
+
+// What the remove curse menu offers: cursed items the player is wearing, or carrying and knows
+// to be cursed.
+#[must_use]
+pub fn cursed_items(ecs: &World) -> Vec<Entity> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let names = ecs.read_storage::<Name>();
+    let dm = ecs.fetch::<MasterDungeonMap>();
+    (
+        &ecs.entities(),
+        &ecs.read_storage::<Item>(),
+        &ecs.read_storage::<CursedItem>(),
+    )
+        .join()
+        .map(|(entity, _, _)| entity)
+        .filter(|item| {
+            // It's equipped, so we know it's cursed
+            equipped
+                .get(*item)
+                .is_some_and(|eq| eq.owner == player_entity)
+                || (backpack
+                    .get(*item)
+                    .is_some_and(|bp| bp.owner == player_entity)
+                    && names
+                        .get(*item)
+                        .is_some_and(|name| dm.identified_items.contains(&name.name)))
+        })
+        .collect()
+}
+
+#[must_use]
+pub fn spell_slot(ecs: &World, spell_entity: Entity) -> Option<usize> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let name = &names.get(spell_entity)?.name;
+    ecs.read_storage::<KnownSpells>()
+        .get(player_entity)?
+        .spells
+        .iter()
+        .position(|spell| &spell.display_name == name)
+}
+
+// Applies a player command to the world, returning the state the game should move to. `runstate`
+// is the state the command was issued from; trading, identifying and removing curses only make
+// sense inside their menus. A command that can't be carried out (an empty slot, too little mana,
+// no stairs underfoot) no longer uses up the turn as the old key handler did: nothing happens,
+// so there is nothing for the turn counter or a replay to account for.
+pub fn apply(ecs: &mut World, command: Command, runstate: RunState) -> RunState {
+    match command {
+        Command::Move { dx, dy } => player::try_move_player(dx, dy, ecs),
+        Command::Wait => player::skip_turn(ecs),
+        Command::PickUp => {
+            player::get_item(ecs);
+            RunState::Ticking
+        }
+        Command::UseItem { slot, target } => use_item(ecs, slot, target),
+        Command::Cast { slot, target } => cast_spell(ecs, slot, target),
+        Command::DropItem { slot } => {
+            let Some(item) = backpack_items(ecs).get(slot).copied() else {
+                return RunState::AwaitingInput;
+            };
+            ecs.write_storage::<WantsToDropItem>()
+                .insert(*ecs.fetch::<Entity>(), WantsToDropItem { item })
+                .expect("Unable to insert intent");
+            RunState::Ticking
+        }
+        Command::RemoveItem { slot } => {
+            let Some(item) = equipped_items(ecs).get(slot).copied() else {
+                return RunState::AwaitingInput;
+            };
+            ecs.write_storage::<WantsToRemoveItem>()
+                .insert(*ecs.fetch::<Entity>(), WantsToRemoveItem { item })
+                .expect("Unable to insert intent");
+            RunState::Ticking
+        }
+        Command::RemoveCurse { slot } => {
+            if runstate != RunState::ShowRemoveCurse {
+                return RunState::AwaitingInput;
+            }
+            let Some(item) = cursed_items(ecs).get(slot).copied() else {
+                return runstate;
+            };
+            ecs.write_storage::<CursedItem>().remove(item);
+            RunState::Ticking
+        }
+        Command::Identify { slot } => {
+            if runstate != RunState::ShowIdentify {
+                return RunState::AwaitingInput;
+            }
+            let Some(item) = identifiable_items(ecs).get(slot).copied() else {
+                return runstate;
+            };
+            if let Some(name) = ecs.read_storage::<Name>().get(item) {
+                let mut dm = ecs.fetch_mut::<MasterDungeonMap>();
+                dm.identified_items.insert(name.name.clone());
+            }
+            RunState::Ticking
+        }
+        Command::CycleTarget => {
+            player::cycle_target(ecs);
+            RunState::AwaitingInput
+        }
+        Command::Fire => player::fire_on_target(ecs),
+        Command::Descend => {
+            if player::try_next_level(ecs) {
+                RunState::NextLevel
+            } else {
+                RunState::AwaitingInput
+            }
+        }
+        Command::Ascend => {
+            if player::try_previous_level(ecs) {
+                RunState::PreviousLevel
+            } else {
+                RunState::AwaitingInput
+            }
+        }
+        Command::Sell { slot } => sell_item(ecs, slot, runstate),
+        Command::Buy { slot } => buy_item(ecs, slot, runstate),
+        Command::Cheat { cheat } => apply_cheat(ecs, cheat),
+        Command::Reload => {
+            crate::saveload_system::reload_game(ecs);
+            RunState::AwaitingInput
+        }
+    }
+}
+
+fn use_item(ecs: &World, slot: usize, target: Option<Point>) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let Some(item) = backpack_items(ecs).get(slot).copied() else {
+        return RunState::AwaitingInput;
+    };
+    if ecs.read_storage::<Ranged>().get(item).is_some() && target.is_none() {
+        return RunState::AwaitingInput;
+    }
+    ecs.write_storage::<WantsToUseItem>()
+        .insert(player_entity, WantsToUseItem { item, target })
+        .expect("Unable to insert intent");
+    RunState::Ticking
+}
+
+fn cast_spell(ecs: &World, slot: usize, target: Option<Point>) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let Some(spell) = ecs
+        .read_storage::<KnownSpells>()
+        .get(player_entity)
+        .and_then(|known| known.spells.get(slot).cloned())
+    else {
+        return RunState::AwaitingInput;
+    };
+
+    let mana = ecs
+        .read_storage::<Pools>()
+        .get(player_entity)
+        .unwrap()
+        .mana
+        .current;
+    if mana < spell.mana_cost {
+        gamelog::Logger::new()
+            .append(&"You don't have enough mana to cast that!")
+            .log();
+        return RunState::AwaitingInput;
+    }
+    let Some(spell_entity) = find_spell_entity(ecs, &spell.display_name) else {
+        return RunState::AwaitingInput;
+    };
+    if ecs
+        .read_storage::<SpellTemplate>()
+        .get(spell_entity)
+        .is_none()
+        || (ecs.read_storage::<Ranged>().get(spell_entity).is_some() && target.is_none())
+    {
+        return RunState::AwaitingInput;
+    }
+    ecs.write_storage::<WantsToCastSpell>()
+        .insert(
+            player_entity,
+            WantsToCastSpell {
+                spell: spell_entity,
+                target,
+            },
+        )
+        .expect("Unable to insert intent");
+    RunState::Ticking
+}
+
+fn sell_item(ecs: &mut World, slot: usize, runstate: RunState) -> RunState {
+    let RunState::ShowVendor { .. } = runstate else {
+        return RunState::AwaitingInput;
+    };
+    let Some(item) = backpack_items(ecs).get(slot).copied() else {
+        return runstate;
+    };
+    let price = ecs.read_storage::<Item>().get(item).unwrap().base_value * 0.8;
+    ecs.write_storage::<Pools>()
+        .get_mut(*ecs.fetch::<Entity>())
+        .unwrap()
+        .gold += price;
+    ecs.delete_entity(item).expect("Unable to delete");
+    runstate
+}
+
+fn buy_item(ecs: &mut World, slot: usize, runstate: RunState) -> RunState {
+    let RunState::ShowVendor { vendor, .. } = runstate else {
+        return RunState::AwaitingInput;
+    };
+    let Some((tag, price)) = ecs.read_storage::<Vendor>().get(vendor).and_then(|v| {
+        get_vendor_items(&v.categories, &RAWS.lock().unwrap())
+            .get(slot)
+            .cloned()
+    }) else {
+        return runstate;
+    };
+
+    let player_entity = *ecs.fetch::<Entity>();
+    ecs.write_storage::<IdentifiedItem>()
+        .insert(player_entity, IdentifiedItem { name: tag.clone() })
+        .expect("Unable to insert");
+    let mut pools = ecs.write_storage::<Pools>();
+    let player_pools = pools.get_mut(player_entity).unwrap();
+    if player_pools.gold >= price {
+        player_pools.gold -= price;
+        std::mem::drop(pools);
+        spawn_named_item(
+            &RAWS.lock().unwrap(),
+            ecs,
+            &tag,
+            SpawnType::Carried { by: player_entity },
+        );
+    }
+    runstate
+}
+
+fn apply_cheat(ecs: &World, cheat: Cheat) -> RunState {
+    match cheat {
+        Cheat::TeleportToExit => return RunState::NextLevel,
+        Cheat::Heal => {
+            let player = ecs.fetch::<Entity>();
+            let mut pools = ecs.write_storage::<Pools>();
+            let player_pools = pools.get_mut(*player).unwrap();
+            player_pools.hit_points.current = player_pools.hit_points.max;
+        }
+        Cheat::Reveal => {
+            let mut map = ecs.fetch_mut::<Map>();
+            for v in &mut map.revealed_tiles {
+                *v = true;
+            }
+        }
+        Cheat::GodMode => {
+            let player = ecs.fetch::<Entity>();
+            let mut pools = ecs.write_storage::<Pools>();
+            let player_pools = pools.get_mut(*player).unwrap();
+            player_pools.god_mode = true;
+        }
+    }
+    RunState::AwaitingInput
+}
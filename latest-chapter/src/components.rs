@@ -4,7 +4,7 @@ use specs::error::NoError;
 use specs::prelude::*;
 use specs::saveload::{ConvertSaveload, Marker};
 use specs_derive::{Component, ConvertSaveload};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Position {
@@ -192,7 +192,7 @@ pub struct Attributes {
     pub intelligence: Attribute,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub enum Skill {
     Melee,
     Defense,
@@ -201,7 +201,7 @@ pub enum Skill {
 
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Skills {
-    pub skills: HashMap<Skill, i32>,
+    pub skills: BTreeMap<Skill, i32>,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -513,6 +513,6 @@ pub struct SerializationHelper {
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct DMSerializationHelper {
     pub map: super::map::MasterDungeonMap,
-    pub log: Vec<Vec<crate::gamelog::LogFragment>>,
+    pub log: Vec<crate::gamelog::LogEntry>,
     pub events: HashMap<String, i32>,
 }
@@ -6,6 +6,7 @@ use crate::components::{
     Attributes, Confusion, DamageOverTime, Duration, EquipmentChanged, Name, Player, Pools,
     SerializeMe, Skills, Slow, StatusEffect,
 };
+use crate::event_bus::{GameEvent, Participant};
 use crate::gamesystem::{mana_at_level, player_hp_at_level};
 use crate::map::Map;
 use specs::saveload::{MarkedBuilder, SimpleMarker};
@@ -42,7 +43,19 @@ pub fn inflict_damage(ecs: &World, damage: &EffectSpawner, target: Entity) {
                     }
                 }
 
+                if crate::event_bus::has_sinks() {
+                    crate::event_bus::emit(&GameEvent::DamageDealt {
+                        source: damage.creator.map(|c| Participant::of(ecs, c)),
+                        target: Participant::of(ecs, target),
+                        amount,
+                        hit_points_left: pool.hit_points.current,
+                    });
+                }
+
                 if pool.hit_points.current < 1 {
+                    if target == *player_entity {
+                        crate::morgue::record_killer(ecs, damage.creator);
+                    }
                     add_effect(
                         damage.creator,
                         EffectType::EntityDeath,
@@ -70,8 +83,16 @@ pub fn death(ecs: &World, effect: &EffectSpawner, target: Entity) {
         crate::spatial::remove_entity(target, pos as usize);
     }
 
+    if crate::event_bus::has_sinks() {
+        crate::event_bus::emit(&GameEvent::EntityKilled {
+            killer: effect.creator.map(|c| Participant::of(ecs, c)),
+            victim: Participant::of(ecs, target),
+        });
+    }
+
     if let Some(source) = effect.creator {
         if ecs.read_storage::<Player>().get(source).is_some() {
+            crate::gamelog::record_event(&"Kills", 1);
             if let Some(stats) = pools.get(target) {
                 xp_gain += stats.level * 100;
                 gold_gain += stats.gold;
@@ -82,9 +103,17 @@ pub fn death(ecs: &World, effect: &EffectSpawner, target: Entity) {
                 let player_attributes = attributes.get_mut(source).unwrap();
                 player_stats.xp += xp_gain;
                 player_stats.gold += gold_gain;
+                if xp_gain != 0 && crate::event_bus::has_sinks() {
+                    crate::event_bus::emit(&GameEvent::XpGained {
+                        amount: xp_gain,
+                        total: player_stats.xp,
+                        level: player_stats.level,
+                    });
+                }
                 if player_stats.xp >= player_stats.level * 1000 {
                     // We've gone up a level!
                     player_stats.level += 1;
+                    crate::gamelog::record_event(&"Level Up", 1);
                     crate::gamelog::Logger::new()
                         .color(rltk::MAGENTA)
                         .append(&"Congratulations, you are now level")
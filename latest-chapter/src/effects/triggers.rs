@@ -25,6 +25,13 @@ pub fn item_trigger(creator: Option<Entity>, item: Entity, targets: &Targets, ec
         c.charges -= 1;
     }
 
+    if crate::event_bus::has_sinks() {
+        crate::event_bus::emit(&crate::event_bus::GameEvent::ItemUsed {
+            user: creator.map(|c| crate::event_bus::Participant::of(ecs, c)),
+            item: crate::event_bus::Participant::of(ecs, item),
+        });
+    }
+
     // Use the item via the generic system
     let did_something = event_trigger(creator, item, targets, ecs);
 
@@ -99,6 +106,7 @@ pub fn trigger(creator: Option<Entity>, trigger: Entity, targets: &Targets, ecs:
     }
 }
 
+// Effects always fire in the order they're checked here, whatever order the raws list them in.
 #[allow(clippy::cognitive_complexity)]
 fn event_trigger(creator: Option<Entity>, entity: Entity, targets: &Targets, ecs: &World) -> bool {
     let mut did_something = false;
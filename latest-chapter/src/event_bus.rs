@@ -0,0 +1,189 @@
+use super::gamelog;
+use serde::Serialize;
+use specs::prelude::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+
+// Who took part in an event. The id tells apart monsters that share a name.
+#[derive(Serialize, Clone, Debug)]
+pub struct Participant {
+    pub id: u32,
+    pub name: String,
+}
+
+impl Participant {
+    #[must_use]
+    pub fn new(entity: Entity, name: &str) -> Self {
+        Self {
+            id: entity.id(),
+            name: name.to_string(),
+        }
+    }
+
+    #[must_use]
+    pub fn of(ecs: &World, entity: Entity) -> Self {
+        let names = ecs.read_storage::<crate::Name>();
+        Self::new(
+            entity,
+            names.get(entity).map_or("Unknown", |n| n.name.as_str()),
+        )
+    }
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttackKind {
+    Melee,
+    Ranged,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttackOutcome {
+    Hit,
+    Miss,
+    NaturalOne,
+}
+
+#[derive(Serialize, Copy, Clone, Debug)]
+pub struct HitRoll {
+    pub natural_roll: i32,
+    pub attribute_bonus: i32,
+    pub skill_bonus: i32,
+    pub weapon_bonus: i32,
+    pub status_bonus: i32,
+    pub total: i32,
+}
+
+impl HitRoll {
+    // Adds the d20 to a roll that so far holds only the bonuses, as `melee_hit_bonus` gives.
+    #[must_use]
+    pub const fn with_roll(self, natural_roll: i32) -> Self {
+        Self {
+            natural_roll,
+            total: self.total + natural_roll,
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize, Copy, Clone, Debug)]
+pub struct ArmorClass {
+    pub base: i32,
+    pub quickness_bonus: i32,
+    pub skill_bonus: i32,
+    pub item_bonus: i32,
+    pub total: i32,
+}
+
+#[derive(Serialize, Copy, Clone, Debug)]
+pub struct DamageRoll {
+    pub base_roll: i32,
+    pub attribute_bonus: i32,
+    pub skill_bonus: i32,
+    pub weapon_bonus: i32,
+    pub total: i32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GameEvent {
+    AttackResolved {
+        kind: AttackKind,
+        attacker: Participant,
+        target: Participant,
+        outcome: AttackOutcome,
+        hit: HitRoll,
+        armor: ArmorClass,
+        damage: Option<DamageRoll>,
+    },
+    DamageDealt {
+        source: Option<Participant>,
+        target: Participant,
+        amount: i32,
+        hit_points_left: i32,
+    },
+    ItemUsed {
+        user: Option<Participant>,
+        item: Participant,
+    },
+    LevelEntered {
+        depth: i32,
+        offset: i32,
+    },
+    EntityKilled {
+        killer: Option<Participant>,
+        victim: Participant,
+    },
+    XpGained {
+        amount: i32,
+        total: i32,
+        level: i32,
+    },
+}
+
+pub trait EventSink: Send {
+    /// Handles one event from the bus.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the sink can't record the event.
+    fn handle(&mut self, turn: i32, event: &GameEvent) -> io::Result<()>;
+}
+
+static SINKS: LazyLock<Mutex<Vec<Box<dyn EventSink>>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+pub fn add_sink(sink: Box<dyn EventSink>) {
+    SINKS.lock().unwrap().push(sink);
+}
+
+// Cheap to check before building an event, which means looking up names.
+#[must_use]
+pub fn has_sinks() -> bool {
+    !SINKS.lock().unwrap().is_empty()
+}
+
+pub fn emit(event: &GameEvent) {
+    let turn = gamelog::get_event_count(&"Turn");
+    let mut sinks = SINKS.lock().unwrap();
+    for sink in sinks.iter_mut() {
+        if let Err(e) = sink.handle(turn, event) {
+            rltk::console::log(format!("Unable to record game event: {e}"));
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    turn: i32,
+    #[serde(flatten)]
+    event: &'a GameEvent,
+}
+
+// One JSON object per line, flushed as it goes so a crash doesn't lose the end of the run.
+pub struct JsonLinesSink {
+    writer: BufWriter<File>,
+}
+
+impl JsonLinesSink {
+    /// Starts a new event file, replacing any that's already there.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be created.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+}
+
+impl EventSink for JsonLinesSink {
+    fn handle(&mut self, turn: i32, event: &GameEvent) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, &Line { turn, event })?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
@@ -1,13 +1,16 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{LazyLock, Mutex};
 
 static EVENTS: LazyLock<Mutex<HashMap<String, i32>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static EVENTS_CHANGED: AtomicBool = AtomicBool::new(false);
 
 pub fn clear_events() {
     EVENTS.lock().unwrap().clear();
 }
 
 pub fn record_event<T: ToString>(event: &T, n: i32) {
+    EVENTS_CHANGED.store(true, Ordering::Relaxed);
     let event_name = event.to_string();
     let mut events_lock = EVENTS.lock();
     let events = events_lock.as_mut().unwrap();
@@ -18,6 +21,11 @@ pub fn record_event<T: ToString>(event: &T, n: i32) {
     }
 }
 
+// Whether any counter has gone up since the last time this was asked.
+pub fn take_events_changed() -> bool {
+    EVENTS_CHANGED.swap(false, Ordering::Relaxed)
+}
+
 pub fn get_event_count<T: ToString>(event: &T) -> i32 {
     let event_name = event.to_string();
     let events = EVENTS.lock().unwrap();
@@ -35,3 +43,30 @@ pub fn load_events(events: &HashMap<String, i32>) {
         EVENTS.lock().unwrap().insert(k.to_string(), *v);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::get_event_count;
+    use crate::command::{Cheat, Command};
+    use crate::headless::{test_lock, Simulation};
+
+    // Achievements, the morgue and the run history all count over the whole run.
+    #[test]
+    fn counters_last_the_whole_run() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(16);
+        for _ in 0..5 {
+            sim.step(Command::Wait);
+        }
+        sim.step(Command::Cheat {
+            cheat: Cheat::TeleportToExit,
+        });
+        sim.step(Command::Descend);
+        assert_eq!(sim.depth(), 2);
+        assert!(sim.turns() > 5);
+        assert_eq!(get_event_count(&"Turn"), sim.turns() as i32);
+
+        sim.state.new_game(17);
+        assert_eq!(get_event_count(&"Turn"), 0);
+    }
+}
@@ -1,42 +1,241 @@
 use super::LogFragment;
 use rltk::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::sync::{LazyLock, Mutex};
 
-static LOG: LazyLock<Mutex<Vec<Vec<LogFragment>>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+// The log lasts the whole run, so only this many of the latest entries are kept.
+const MAX_ENTRIES: usize = 1000;
+const HUD_LOG_LINES: usize = 6;
+
+// A message and how many times in a row it was logged.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LogEntry {
+    pub fragments: Vec<LogFragment>,
+    pub repeats: usize,
+}
+
+impl LogEntry {
+    fn display(&self) -> Vec<LogFragment> {
+        let mut fragments = self.fragments.clone();
+        if self.repeats > 1 {
+            fragments.push(LogFragment {
+                color: RGB::named(rltk::GRAY),
+                text: format!("(x{})", self.repeats),
+            });
+        }
+        fragments
+    }
+}
+
+static LOG: LazyLock<Mutex<Vec<LogEntry>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+fn same_message(a: &[LogFragment], b: &[LogFragment]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, b)| a.text == b.text && a.color == b.color)
+}
 
 pub fn append_entry(fragments: Vec<LogFragment>) {
-    LOG.lock().unwrap().push(fragments);
+    let mut log = LOG.lock().unwrap();
+    if let Some(last) = log.last_mut() {
+        if same_message(&last.fragments, &fragments) {
+            last.repeats += 1;
+            return;
+        }
+    }
+    log.push(LogEntry {
+        fragments,
+        repeats: 1,
+    });
+    if log.len() > MAX_ENTRIES {
+        let excess = log.len() - MAX_ENTRIES;
+        log.drain(..excess);
+    }
 }
 
 pub fn clear_log() {
     LOG.lock().unwrap().clear();
 }
 
-pub fn print_log(console: &mut Box<dyn Console>, pos: Point) {
-    let mut y = pos.y;
-    let mut x = pos.x;
-    LOG.lock().unwrap().iter().rev().take(6).for_each(|log| {
-        for frag in log {
+// Splits a message into lines of at most `width` characters, breaking between words where it
+// can. Fragments are separated by a space, as they are when printed.
+#[must_use]
+pub fn wrap_fragments(fragments: &[LogFragment], width: usize) -> Vec<Vec<LogFragment>> {
+    // Nothing fits on a line with no room, and splitting words to fit it would never finish
+    if width == 0 {
+        return vec![Vec::new()];
+    }
+    let mut lines: Vec<Vec<LogFragment>> = vec![Vec::new()];
+    let mut used = 0;
+    for frag in fragments {
+        for word in frag.text.split_whitespace() {
+            let mut word = word;
+            loop {
+                let len = word.chars().count();
+                let gap = usize::from(used > 0);
+                if used + gap + len <= width {
+                    let line = lines.last_mut().unwrap();
+                    match line.last_mut() {
+                        Some(last) if last.color == frag.color => {
+                            last.text.push(' ');
+                            last.text.push_str(word);
+                        }
+                        _ => line.push(LogFragment {
+                            color: frag.color,
+                            text: if gap > 0 {
+                                format!(" {word}")
+                            } else {
+                                word.to_string()
+                            },
+                        }),
+                    }
+                    used += gap + len;
+                    break;
+                }
+                if used > 0 {
+                    lines.push(Vec::new());
+                    used = 0;
+                    continue;
+                }
+                // A single word wider than the line is split wherever it runs out
+                let split = word
+                    .char_indices()
+                    .nth(width)
+                    .map_or(word.len(), |(i, _)| i);
+                lines.last_mut().unwrap().push(LogFragment {
+                    color: frag.color,
+                    text: word[..split].to_string(),
+                });
+                lines.push(Vec::new());
+                word = &word[split..];
+                if word.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+    if lines.len() > 1 && lines.last().is_some_and(Vec::is_empty) {
+        lines.pop();
+    }
+    lines
+}
+
+fn latest_lines(width: usize) -> Vec<Vec<LogFragment>> {
+    LOG.lock()
+        .unwrap()
+        .iter()
+        .rev()
+        .flat_map(|entry| wrap_fragments(&entry.display(), width))
+        .take(HUD_LOG_LINES)
+        .collect()
+}
+
+// Newest message first, wrapped to fit between `pos.x` and the right edge of the log panel.
+pub fn print_log(console: &mut Box<dyn Console>, pos: Point, width: i32) {
+    for (y, line) in latest_lines(width as usize).into_iter().enumerate() {
+        let mut x = pos.x;
+        for frag in line {
             console.print_color(
                 x,
-                y,
+                pos.y + y as i32,
                 frag.color.to_rgba(1.0),
                 RGBA::named(rltk::BLACK),
                 &frag.text,
             );
-            x += frag.text.len() as i32;
-            x += 1;
+            x += frag.text.chars().count() as i32;
         }
-        y += 1;
-        x = pos.x;
-    });
+    }
 }
 
-pub fn clone_log() -> Vec<Vec<crate::gamelog::LogFragment>> {
+// Every message as it should be shown, with repeats folded into a count. Oldest first.
+#[must_use]
+pub fn log_entries() -> Vec<Vec<LogFragment>> {
+    LOG.lock().unwrap().iter().map(LogEntry::display).collect()
+}
+
+// The most recent entries as plain text, oldest first.
+pub fn log_lines(count: usize) -> Vec<String> {
+    let log = LOG.lock().unwrap();
+    log[log.len().saturating_sub(count)..]
+        .iter()
+        .map(|entry| {
+            entry
+                .display()
+                .iter()
+                .map(|frag| frag.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+// Saved with repeats still folded, so a run of the same message costs one entry.
+pub fn clone_log() -> Vec<LogEntry> {
     LOG.lock().unwrap().clone()
 }
 
-pub fn restore_log(log: &mut Vec<Vec<crate::gamelog::LogFragment>>) {
-    LOG.lock().unwrap().clear();
-    LOG.lock().unwrap().append(log);
+pub fn restore_log(log: Vec<LogEntry>) {
+    let mut entries = log;
+    let excess = entries.len().saturating_sub(MAX_ENTRIES);
+    entries.drain(..excess);
+    *LOG.lock().unwrap() = entries;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        append_entry, clear_log, clone_log, log_lines, restore_log, wrap_fragments, LogFragment,
+    };
+    use rltk::RGB;
+
+    fn fragment(text: &str) -> LogFragment {
+        LogFragment {
+            color: RGB::named(rltk::WHITE),
+            text: text.to_string(),
+        }
+    }
+
+    fn texts(lines: &[Vec<LogFragment>]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|frag| frag.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn messages_wrap_between_words() {
+        let lines = wrap_fragments(&[fragment("The goblin hits you"), fragment("hard")], 10);
+        assert_eq!(texts(&lines), ["The goblin", "hits you", "hard"]);
+    }
+
+    #[test]
+    fn a_word_wider_than_the_line_is_split() {
+        let lines = wrap_fragments(&[fragment("Aaaaargh!")], 4);
+        assert_eq!(texts(&lines), ["Aaaa", "argh", "!"]);
+    }
+
+    #[test]
+    fn nothing_is_wrapped_into_no_room() {
+        assert_eq!(texts(&wrap_fragments(&[fragment("You wait")], 0)), [""]);
+    }
+
+    #[test]
+    fn repeated_messages_are_folded_and_saved_folded() {
+        let _lock = crate::headless::test_lock();
+        clear_log();
+        for _ in 0..3 {
+            append_entry(vec![fragment("You wait")]);
+        }
+        append_entry(vec![fragment("The rat bites")]);
+        append_entry(vec![fragment("You wait")]);
+        assert_eq!(log_lines(3), ["You wait (x3)", "The rat bites", "You wait"]);
+
+        let saved = clone_log();
+        assert_eq!(saved.len(), 3);
+        clear_log();
+        restore_log(saved);
+        assert_eq!(log_lines(3), ["You wait (x3)", "The rat bites", "You wait"]);
+        clear_log();
+    }
 }
@@ -3,7 +3,9 @@ mod builder;
 pub use builder::*;
 mod logstore;
 use logstore::append_entry;
-pub use logstore::{clear_log, clone_log, print_log, restore_log};
+pub use logstore::{
+    clear_log, clone_log, log_entries, log_lines, print_log, restore_log, wrap_fragments, LogEntry,
+};
 use serde::{Deserialize, Serialize};
 mod events;
 pub use events::*;
@@ -0,0 +1,217 @@
+use super::get_item_display_name;
+use crate::systems::{armor_class, melee_hit_bonus, unarmed_attack};
+use crate::{
+    Attribute, Attributes, Duration, EquipmentSlot, Equipped, HungerClock, HungerState, Name,
+    NaturalAttackDefense, Pools, Skills, State, StatusEffect, Weapon, Wearable,
+};
+use rltk::prelude::*;
+use specs::prelude::*;
+
+const LEFT: i32 = 2;
+const TOP: i32 = 2;
+const WIDTH: i32 = 75;
+const HEIGHT: i32 = 55;
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum CharacterSheetResult {
+    NoResponse,
+    Close,
+}
+
+struct Sheet<'a> {
+    draw_batch: &'a mut DrawBatch,
+    y: i32,
+}
+
+impl Sheet<'_> {
+    fn heading(&mut self, text: &str) {
+        self.y += 1;
+        self.draw_batch.print_color(
+            Point::new(LEFT + 2, self.y),
+            text,
+            ColorPair::new(RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK)),
+        );
+        self.y += 1;
+    }
+
+    fn row(&mut self, label: &str, value: &str) {
+        let black = RGB::named(rltk::BLACK);
+        self.draw_batch.print_color(
+            Point::new(LEFT + 4, self.y),
+            label,
+            ColorPair::new(RGB::named(rltk::CYAN), black),
+        );
+        self.draw_batch.print_color(
+            Point::new(LEFT + 26, self.y),
+            value,
+            ColorPair::new(RGB::named(rltk::WHITE), black),
+        );
+        self.y += 1;
+    }
+
+    fn attribute(&mut self, label: &str, attribute: &Attribute) {
+        self.row(
+            label,
+            &format!(
+                "{:>4} {:>+9} {:>+6}",
+                attribute.base, attribute.modifiers, attribute.bonus
+            ),
+        );
+    }
+}
+
+// Everything the combat systems know about the player, laid out in full.
+pub fn show_character_sheet(gs: &State, ctx: &Rltk) -> CharacterSheetResult {
+    let ecs = &gs.ecs;
+    let player_entity = *ecs.fetch::<Entity>();
+    let black = RGB::named(rltk::BLACK);
+    let mut draw_batch = DrawBatch::new();
+    let mut sheet = Sheet {
+        draw_batch: &mut draw_batch,
+        y: TOP + 1,
+    };
+    sheet.draw_batch.draw_double_box(
+        Rect::with_size(LEFT, TOP, WIDTH, HEIGHT),
+        ColorPair::new(RGB::named(rltk::WHEAT), black),
+    );
+    sheet.draw_batch.fill_region(
+        Rect::with_size(LEFT + 1, TOP + 1, WIDTH - 1, HEIGHT - 1),
+        ColorPair::new(RGB::named(rltk::WHITE), black),
+        to_cp437(' '),
+    );
+    sheet.draw_batch.print_color_centered(
+        TOP,
+        " Character ",
+        ColorPair::new(RGB::named(rltk::YELLOW), black),
+    );
+
+    let attributes = ecs.read_storage::<Attributes>();
+    let skills = ecs.read_storage::<Skills>();
+    let pools = ecs.read_storage::<Pools>();
+    let hunger = ecs.read_storage::<HungerClock>();
+    let attr = attributes.get(player_entity).unwrap();
+    let player_skills = skills.get(player_entity).unwrap();
+    let player_pools = pools.get(player_entity).unwrap();
+
+    sheet.heading("Attributes                Base Modifiers  Bonus");
+    sheet.attribute("Might", &attr.might);
+    sheet.attribute("Quickness", &attr.quickness);
+    sheet.attribute("Fitness", &attr.fitness);
+    sheet.attribute("Intelligence", &attr.intelligence);
+
+    sheet.heading("Skills");
+    for (skill, level) in &player_skills.skills {
+        sheet.row(&format!("{skill:?}"), &format!("{level:>+4}"));
+    }
+
+    sheet.heading("Combat");
+    let entities = ecs.entities();
+    let equipped = ecs.read_storage::<Equipped>();
+    let weapons = ecs.read_storage::<Weapon>();
+    let wielded = (&entities, &equipped, &weapons)
+        .join()
+        .find(|(_, equipped, _)| {
+            equipped.owner == player_entity && equipped.slot == EquipmentSlot::Melee
+        });
+    let (weapon_name, weapon) = wielded.map_or_else(
+        || ("Bare hands".to_string(), unarmed_attack()),
+        |(entity, _, weapon)| (get_item_display_name(ecs, entity), weapon.clone()),
+    );
+    sheet.row("Melee weapon", &weapon_name);
+    let hit = melee_hit_bonus(&weapon, attr, player_skills, hunger.get(player_entity));
+    sheet.row(
+        "Melee hit bonus",
+        &format!(
+            "{:+} (attribute {:+}, skill {:+}, weapon {:+}, status {:+})",
+            hit.total, hit.attribute_bonus, hit.skill_bonus, hit.weapon_bonus, hit.status_bonus
+        ),
+    );
+    let armor = armor_class(
+        player_entity,
+        attr,
+        player_skills,
+        &equipped,
+        &ecs.read_storage::<Wearable>(),
+        &ecs.read_storage::<NaturalAttackDefense>(),
+    );
+    sheet.row(
+        "Armor class",
+        &format!(
+            "{} (base {}, quickness {:+}, skill {:+}, items {:+})",
+            armor.total, armor.base, armor.quickness_bonus, armor.skill_bonus, armor.item_bonus
+        ),
+    );
+
+    sheet.heading("Condition");
+    sheet.row(
+        "Health",
+        &format!(
+            "{}/{}",
+            player_pools.hit_points.current, player_pools.hit_points.max
+        ),
+    );
+    sheet.row(
+        "Mana",
+        &format!("{}/{}", player_pools.mana.current, player_pools.mana.max),
+    );
+    sheet.row(
+        "Level",
+        &format!("{} ({} xp)", player_pools.level, player_pools.xp),
+    );
+    // The same capacity the encumbrance system checks against
+    let carry_capacity_lbs = (attr.might.base + attr.might.modifiers) * 15;
+    let burden = if player_pools.total_weight as i32 > carry_capacity_lbs {
+        ", overburdened"
+    } else {
+        ""
+    };
+    sheet.row(
+        "Carrying",
+        &format!(
+            "{:.0} lbs of {carry_capacity_lbs} lbs{burden}",
+            player_pools.total_weight
+        ),
+    );
+    sheet.row(
+        "Initiative penalty",
+        &format!("{:.0}", player_pools.total_initiative_penalty),
+    );
+    let hunger_state = match hunger.get(player_entity).map(|clock| clock.state) {
+        Some(HungerState::WellFed) => "Well fed",
+        Some(HungerState::Normal) | None => "Normal",
+        Some(HungerState::Hungry) => "Hungry",
+        Some(HungerState::Starving) => "Starving",
+    };
+    sheet.row("Hunger", hunger_state);
+
+    sheet.heading("Status Effects");
+    let statuses = ecs.read_storage::<StatusEffect>();
+    let durations = ecs.read_storage::<Duration>();
+    let names = ecs.read_storage::<Name>();
+    let mut any_status = false;
+    for (entity, status, name) in (&entities, &statuses, &names).join() {
+        if status.target == player_entity {
+            let remaining = durations
+                .get(entity)
+                .map_or_else(|| "Lasting".to_string(), |d| format!("{} turns", d.turns));
+            sheet.row(&name.name, &remaining);
+            any_status = true;
+        }
+    }
+    if !any_status {
+        sheet.row("None", "");
+    }
+
+    sheet.draw_batch.print_color(
+        Point::new(LEFT + 2, TOP + HEIGHT - 2),
+        "ESCAPE to close",
+        ColorPair::new(RGB::named(rltk::GRAY), black),
+    );
+
+    let _result = draw_batch.submit(6000);
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => CharacterSheetResult::Close,
+        _ => CharacterSheetResult::NoResponse,
+    }
+}
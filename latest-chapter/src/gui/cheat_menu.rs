@@ -10,11 +10,12 @@ pub enum CheatMenuResult {
     Heal,
     Reveal,
     GodMode,
+    ReloadRaws,
 }
 
 pub fn show_cheat_mode(_gs: &mut State, ctx: &Rltk) -> CheatMenuResult {
     let mut draw_batch = DrawBatch::new();
-    let count = 4;
+    let count = 5;
     let mut y = 25 - (count / 2);
     menu_box(&mut draw_batch, 15, y, count + 3, &"Cheating!");
     draw_batch.print_color(
@@ -54,6 +55,14 @@ pub fn show_cheat_mode(_gs: &mut State, ctx: &Rltk) -> CheatMenuResult {
         rltk::to_cp437('G'),
         &"God Mode (No Death)",
     );
+    y += 1;
+    menu_option(
+        &mut draw_batch,
+        17,
+        y,
+        rltk::to_cp437('L'),
+        &"Reload raws from disk",
+    );
 
     let _result = draw_batch.submit(6000);
 
@@ -63,6 +72,7 @@ pub fn show_cheat_mode(_gs: &mut State, ctx: &Rltk) -> CheatMenuResult {
             VirtualKeyCode::H => CheatMenuResult::Heal,
             VirtualKeyCode::R => CheatMenuResult::Reveal,
             VirtualKeyCode::G => CheatMenuResult::GodMode,
+            VirtualKeyCode::L => CheatMenuResult::ReloadRaws,
             VirtualKeyCode::Escape => CheatMenuResult::Cancel,
             _ => CheatMenuResult::NoResponse,
         })
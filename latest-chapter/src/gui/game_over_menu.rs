@@ -1,4 +1,7 @@
+use crate::map::MasterDungeonMap;
 use rltk::prelude::*;
+use specs::prelude::*;
+use std::path::Path;
 
 #[derive(PartialEq, Eq, Copy, Clone)]
 pub enum GameOverResult {
@@ -6,7 +9,7 @@ pub enum GameOverResult {
     QuitToMenu,
 }
 
-pub fn game_over(ctx: &Rltk) -> GameOverResult {
+pub fn game_over(ecs: &World, ctx: &Rltk, morgue_file: Option<&Path>) -> GameOverResult {
     let mut draw_batch = DrawBatch::new();
     draw_batch.print_color_centered(
         15,
@@ -49,12 +52,26 @@ pub fn game_over(ctx: &Rltk) -> GameOverResult {
         ColorPair::new(RGB::named(rltk::RED), RGB::named(rltk::BLACK)),
     );
 
+    draw_batch.print_color_centered(
+        22,
+        format!("World seed: {}", ecs.fetch::<MasterDungeonMap>().seed),
+        ColorPair::new(RGB::named(rltk::GRAY), RGB::named(rltk::BLACK)),
+    );
+
     draw_batch.print_color_centered(
         23,
         "Press any key to return to the menu.",
         ColorPair::new(RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK)),
     );
 
+    if let Some(morgue_file) = morgue_file {
+        draw_batch.print_color_centered(
+            25,
+            format!("Morgue file: {}", morgue_file.display()),
+            ColorPair::new(RGB::named(rltk::GRAY), RGB::named(rltk::BLACK)),
+        );
+    }
+
     let _result = draw_batch.submit(6000);
 
     match ctx.key {
@@ -345,7 +345,7 @@ fn status(ecs: &World, draw_batch: &mut DrawBatch, player_entity: Entity) {
     }
 }
 
-pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
+pub fn draw_ui(ecs: &World, ctx: &Rltk) {
     let mut draw_batch = DrawBatch::new();
     let player_entity = *ecs.fetch::<Entity>();
 
@@ -361,6 +361,7 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     gamelog::print_log(
         &mut rltk::BACKEND_INTERNAL.lock().consoles[1].console,
         Point::new(1, 23),
+        77,
     );
     draw_tooltips(ecs, ctx);
 
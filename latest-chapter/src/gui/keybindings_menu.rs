@@ -0,0 +1,77 @@
+use crate::State;
+use rltk::prelude::*;
+
+const LEFT: i32 = 2;
+const TOP: i32 = 2;
+const WIDTH: i32 = 75;
+const HEIGHT: i32 = 55;
+const TEXT_WIDTH: usize = WIDTH as usize - 3;
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum KeybindingsResult {
+    NoResponse,
+    Close,
+}
+
+pub fn show_keybindings(gs: &State, ctx: &Rltk) -> KeybindingsResult {
+    let mut draw_batch = DrawBatch::new();
+    let black = RGB::named(rltk::BLACK);
+    draw_batch.draw_double_box(
+        Rect::with_size(LEFT, TOP, WIDTH, HEIGHT),
+        ColorPair::new(RGB::named(rltk::WHEAT), black),
+    );
+    draw_batch.fill_region(
+        Rect::with_size(LEFT + 1, TOP + 1, WIDTH - 1, HEIGHT - 1),
+        ColorPair::new(RGB::named(rltk::WHITE), black),
+        to_cp437(' '),
+    );
+    draw_batch.print_color_centered(
+        TOP,
+        " Key Bindings ",
+        ColorPair::new(RGB::named(rltk::YELLOW), black),
+    );
+
+    for (row, (action, chords)) in gs.keymap.bindings.iter().enumerate() {
+        let y = TOP + 2 + row as i32;
+        draw_batch.print_color(
+            Point::new(LEFT + 2, y),
+            action.label(),
+            ColorPair::new(RGB::named(rltk::CYAN), black),
+        );
+        let mut keys = if chords.is_empty() {
+            "(unbound)".to_string()
+        } else {
+            chords
+                .iter()
+                .map(|chord| chord.label())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        keys.truncate(TEXT_WIDTH - 22);
+        draw_batch.print_color(
+            Point::new(LEFT + 24, y),
+            keys,
+            ColorPair::new(RGB::named(rltk::WHITE), black),
+        );
+    }
+
+    let mut source = format!("From {}", gs.keymap.source);
+    source.truncate(TEXT_WIDTH);
+    draw_batch.print_color(
+        Point::new(LEFT + 2, TOP + HEIGHT - 4),
+        source,
+        ColorPair::new(RGB::named(rltk::GRAY), black),
+    );
+    draw_batch.print_color(
+        Point::new(LEFT + 2, TOP + HEIGHT - 2),
+        "Edit the file and restart to remap keys. ESCAPE to close",
+        ColorPair::new(RGB::named(rltk::GRAY), black),
+    );
+
+    let _result = draw_batch.submit(6000);
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => KeybindingsResult::Close,
+        _ => KeybindingsResult::NoResponse,
+    }
+}
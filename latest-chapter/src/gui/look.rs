@@ -0,0 +1,276 @@
+use super::get_item_display_name;
+use crate::gamelog::{wrap_fragments, LogFragment};
+use crate::keybindings::Action;
+use crate::raws::{faction_reaction, get_description, Reaction, RAWS};
+use crate::{
+    camera, Duration, Equipped, Faction, Hidden, Map, Name, ObfuscatedName, Pools, State,
+    StatusEffect,
+};
+use rltk::prelude::*;
+use specs::prelude::*;
+
+const PANEL_WIDTH: i32 = 46;
+const TEXT_WIDTH: usize = PANEL_WIDTH as usize - 3;
+const MAX_LINES: usize = 18;
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum LookResult {
+    NoResponse {
+        x: i32,
+        y: i32,
+        index: usize,
+        detail: bool,
+    },
+    Close,
+}
+
+// Everything the player can see standing on a tile, in the order they're cycled through.
+fn visible_contents(ecs: &World, map: &Map, x: i32, y: i32) -> Vec<Entity> {
+    let idx = map.xy_idx(x, y);
+    if !map.visible_tiles[idx] {
+        return Vec::new();
+    }
+    let hidden = ecs.read_storage::<Hidden>();
+    let mut contents = Vec::new();
+    crate::spatial::for_each_tile_content(idx, |entity| {
+        if hidden.get(entity).is_none() {
+            contents.push(entity);
+        }
+    });
+    contents
+}
+
+fn health_estimate(pools: &Pools) -> &'static str {
+    let percent = pools.hit_points.current * 100 / pools.hit_points.max.max(1);
+    match percent {
+        100.. => "Unhurt",
+        75..=99 => "Scratched",
+        50..=74 => "Wounded",
+        25..=49 => "Badly wounded",
+        _ => "Near death",
+    }
+}
+
+fn line(label: &str, value: &str) -> Vec<LogFragment> {
+    vec![
+        LogFragment {
+            color: RGB::named(rltk::CYAN),
+            text: format!("{label}:"),
+        },
+        LogFragment {
+            color: RGB::named(rltk::WHITE),
+            text: value.to_string(),
+        },
+    ]
+}
+
+// The lines of the detail panel for one entity. Descriptions of unidentified items are kept
+// back, since they would give the game away.
+fn describe(ecs: &World, entity: Entity) -> Vec<Vec<LogFragment>> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut lines = Vec::new();
+
+    if let Some(faction) = ecs.read_storage::<Faction>().get(entity) {
+        if entity != player_entity {
+            let reaction = faction_reaction(&faction.name, "Player", &RAWS.lock().unwrap());
+            let attitude = match reaction {
+                Reaction::Attack => "Hostile",
+                Reaction::Flee => "Afraid of you",
+                Reaction::Ignore => "Not interested in you",
+            };
+            lines.push(line("Attitude", attitude));
+        }
+    }
+
+    if let Some(pools) = ecs.read_storage::<Pools>().get(entity) {
+        lines.push(line("Level", &pools.level.to_string()));
+        lines.push(line("Health", health_estimate(pools)));
+    }
+
+    let entities = ecs.entities();
+    let equipped = ecs.read_storage::<Equipped>();
+    let equipment: Vec<String> = (&entities, &equipped)
+        .join()
+        .filter(|(_, equipped)| equipped.owner == entity)
+        .map(|(item, _)| get_item_display_name(ecs, item))
+        .collect();
+    if !equipment.is_empty() {
+        lines.push(line("Equipped", &equipment.join(", ")));
+    }
+
+    let statuses = ecs.read_storage::<StatusEffect>();
+    let durations = ecs.read_storage::<Duration>();
+    let names = ecs.read_storage::<Name>();
+    let effects: Vec<String> = (&statuses, &durations, &names)
+        .join()
+        .filter(|(status, _, _)| status.target == entity)
+        .map(|(_, duration, name)| format!("{} ({})", name.name, duration.turns))
+        .collect();
+    if !effects.is_empty() {
+        lines.push(line("Status", &effects.join(", ")));
+    }
+
+    if let Some(name) = names.get(entity) {
+        let identified = ecs.read_storage::<ObfuscatedName>().get(entity).is_none()
+            || ecs
+                .fetch::<crate::map::MasterDungeonMap>()
+                .identified_items
+                .contains(&name.name);
+        let description = if identified {
+            get_description(&name.name, &RAWS.lock().unwrap())
+        } else {
+            None
+        };
+        if let Some(description) = description {
+            lines.push(Vec::new());
+            lines.push(vec![LogFragment {
+                color: RGB::named(rltk::LIGHT_GRAY),
+                text: description,
+            }]);
+        }
+    }
+
+    lines
+        .iter()
+        .flat_map(|line| wrap_fragments(line, TEXT_WIDTH))
+        .take(MAX_LINES)
+        .collect()
+}
+
+fn draw_panel(draw_batch: &mut DrawBatch, top: i32, title: &str, lines: &[Vec<LogFragment>]) {
+    let black = RGB::named(rltk::BLACK);
+    let height = lines.len() as i32 + 3;
+    draw_batch.draw_box(
+        Rect::with_size(2, top, PANEL_WIDTH - 1, height),
+        ColorPair::new(RGB::named(rltk::WHEAT), black),
+    );
+    draw_batch.print_color(
+        Point::new(4, top),
+        format!(" {title} "),
+        ColorPair::new(RGB::named(rltk::YELLOW), black),
+    );
+    for (row, line) in lines.iter().enumerate() {
+        let mut x = 4;
+        for frag in line {
+            draw_batch.print_color(
+                Point::new(x, top + 2 + row as i32),
+                &frag.text,
+                ColorPair::new(frag.color, black),
+            );
+            x += frag.text.chars().count() as i32;
+        }
+    }
+}
+
+// Moves a cursor about the map with the movement keys. Tab cycles through everything on the
+// tile, Enter shows or hides the details of the one selected.
+pub fn show_look(
+    gs: &State,
+    ctx: &Rltk,
+    (x, y): (i32, i32),
+    index: usize,
+    detail: bool,
+) -> LookResult {
+    let (min_x, max_x, min_y, max_y) = camera::get_screen_bounds(&gs.ecs, ctx);
+    let map = gs.ecs.fetch::<Map>();
+    let contents = visible_contents(&gs.ecs, &map, x, y);
+    let index = if contents.is_empty() {
+        0
+    } else {
+        index % contents.len()
+    };
+
+    let mut draw_batch = DrawBatch::new();
+    let black = RGB::named(rltk::BLACK);
+    let screen = Point::new(x - min_x + 1, y - min_y + 1);
+    let visible = map.visible_tiles[map.xy_idx(x, y)];
+    draw_batch.set_bg(
+        screen,
+        RGB::named(if visible { rltk::CYAN } else { rltk::GRAY }),
+    );
+
+    let heading = match contents.get(index) {
+        _ if !visible => "You can't see there.".to_string(),
+        None => "Nothing of interest.".to_string(),
+        Some(entity) if contents.len() > 1 => format!(
+            "{} ({}/{})",
+            get_item_display_name(&gs.ecs, *entity),
+            index + 1,
+            contents.len()
+        ),
+        Some(entity) => get_item_display_name(&gs.ecs, *entity),
+    };
+    draw_batch.print_color(
+        Point::new(5, 0),
+        format!("Look: {heading}"),
+        ColorPair::new(RGB::named(rltk::YELLOW), black),
+    );
+
+    if let (true, Some(entity)) = (detail, contents.get(index)) {
+        let lines = describe(&gs.ecs, *entity);
+        // Kept to the half of the map the cursor isn't in
+        let top = if screen.y < (max_y - min_y) / 2 {
+            max_y - min_y - lines.len() as i32 - 3
+        } else {
+            2
+        };
+        let title = get_item_display_name(&gs.ecs, *entity);
+        draw_panel(&mut draw_batch, top, &title, &lines);
+    }
+    draw_batch.print_color(
+        Point::new(3, max_y - min_y + 1),
+        "Move to look, Tab to cycle, Enter for details, ESCAPE to finish",
+        ColorPair::new(RGB::named(rltk::GRAY), black),
+    );
+
+    let _result = draw_batch.submit(5000);
+
+    let Some(key) = ctx.key else {
+        return LookResult::NoResponse {
+            x,
+            y,
+            index,
+            detail,
+        };
+    };
+    match key {
+        VirtualKeyCode::Escape => return LookResult::Close,
+        VirtualKeyCode::Tab => {
+            return LookResult::NoResponse {
+                x,
+                y,
+                index: index + 1,
+                detail,
+            }
+        }
+        VirtualKeyCode::Return => {
+            return LookResult::NoResponse {
+                x,
+                y,
+                index,
+                detail: !detail,
+            }
+        }
+        _ => {}
+    }
+    let (dx, dy) = match gs.keymap.action(key, ctx.shift, ctx.control) {
+        Some(Action::MoveWest) => (-1, 0),
+        Some(Action::MoveEast) => (1, 0),
+        Some(Action::MoveNorth) => (0, -1),
+        Some(Action::MoveSouth) => (0, 1),
+        Some(Action::MoveNorthEast) => (1, -1),
+        Some(Action::MoveNorthWest) => (-1, -1),
+        Some(Action::MoveSouthEast) => (1, 1),
+        Some(Action::MoveSouthWest) => (-1, 1),
+        _ => (0, 0),
+    };
+    // The cursor stays on the map and on screen
+    let new_x = (x + dx).clamp(1.max(min_x), (map.width - 2).min(max_x - 1));
+    let new_y = (y + dy).clamp(1.max(min_y), (map.height - 2).min(max_y - 1));
+    LookResult::NoResponse {
+        x: new_x,
+        y: new_y,
+        index: if (new_x, new_y) == (x, y) { index } else { 0 },
+        detail,
+    }
+}
@@ -1,10 +1,13 @@
+use crate::saveload_system::{SaveHeader, AUTOSAVE_SLOT, SAVE_SLOTS};
 use crate::{rex_assets::RexAssets, RunState, State};
 use rltk::prelude::*;
 
 #[derive(PartialEq, Eq, Copy, Clone)]
 pub enum MainMenuSelection {
     NewGame,
+    SeededGame,
     LoadGame,
+    RunHistory,
     Quit,
 }
 
@@ -12,6 +15,8 @@ pub enum MainMenuSelection {
 pub enum MainMenuResult {
     NoSelection { selected: MainMenuSelection },
     Selected { selected: MainMenuSelection },
+    RecoverAutosave,
+    DismissRecovery { selected: MainMenuSelection },
 }
 
 pub fn main_menu(gs: &State, ctx: &mut Rltk) -> MainMenuResult {
@@ -22,7 +27,7 @@ pub fn main_menu(gs: &State, ctx: &mut Rltk) -> MainMenuResult {
     ctx.render_xp_sprite(&assets.menu, 0, 0);
 
     draw_batch.draw_double_box(
-        Rect::with_size(24, 18, 31, 10),
+        Rect::with_size(24, 18, 31, 11),
         ColorPair::new(RGB::named(rltk::WHEAT), RGB::named(rltk::BLACK)),
     );
 
@@ -62,6 +67,21 @@ pub fn main_menu(gs: &State, ctx: &mut Rltk) -> MainMenuResult {
         }
         y += 1;
 
+        if selection == MainMenuSelection::SeededGame {
+            draw_batch.print_color_centered(
+                y,
+                "Begin Seeded Game",
+                ColorPair::new(RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK)),
+            );
+        } else {
+            draw_batch.print_color_centered(
+                y,
+                "Begin Seeded Game",
+                ColorPair::new(RGB::named(rltk::WHITE), RGB::named(rltk::BLACK)),
+            );
+        }
+        y += 1;
+
         if save_exists {
             if selection == MainMenuSelection::LoadGame {
                 draw_batch.print_color_centered(
@@ -79,6 +99,21 @@ pub fn main_menu(gs: &State, ctx: &mut Rltk) -> MainMenuResult {
             y += 1;
         }
 
+        if selection == MainMenuSelection::RunHistory {
+            draw_batch.print_color_centered(
+                y,
+                "Past Runs",
+                ColorPair::new(RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK)),
+            );
+        } else {
+            draw_batch.print_color_centered(
+                y,
+                "Past Runs",
+                ColorPair::new(RGB::named(rltk::WHITE), RGB::named(rltk::BLACK)),
+            );
+        }
+        y += 1;
+
         if selection == MainMenuSelection::Quit {
             draw_batch.print_color_centered(
                 y,
@@ -93,6 +128,39 @@ pub fn main_menu(gs: &State, ctx: &mut Rltk) -> MainMenuResult {
             );
         }
 
+        if let Some(message) = &gs.menu_message {
+            draw_batch.print_color_centered(
+                30,
+                message,
+                ColorPair::new(RGB::named(rltk::RED), RGB::named(rltk::BLACK)),
+            );
+        }
+
+        if gs.recovery_prompt {
+            draw_batch.print_color_centered(
+                32,
+                "The last game didn't exit cleanly.",
+                ColorPair::new(RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK)),
+            );
+            draw_batch.print_color_centered(
+                33,
+                "Recover it from the autosave? (Y/N)",
+                ColorPair::new(RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK)),
+            );
+            let _result = draw_batch.submit(6000);
+            return match ctx.key {
+                Some(VirtualKeyCode::Y) => MainMenuResult::RecoverAutosave,
+                Some(VirtualKeyCode::N | VirtualKeyCode::Escape) => {
+                    MainMenuResult::DismissRecovery {
+                        selected: selection,
+                    }
+                }
+                _ => MainMenuResult::NoSelection {
+                    selected: selection,
+                },
+            };
+        }
+
         let _result = draw_batch.submit(6000);
 
         match ctx.key {
@@ -111,11 +179,13 @@ pub fn main_menu(gs: &State, ctx: &mut Rltk) -> MainMenuResult {
                     let mut newselection;
                     match selection {
                         MainMenuSelection::NewGame => newselection = MainMenuSelection::Quit,
-                        MainMenuSelection::LoadGame => newselection = MainMenuSelection::NewGame,
-                        MainMenuSelection::Quit => newselection = MainMenuSelection::LoadGame,
+                        MainMenuSelection::SeededGame => newselection = MainMenuSelection::NewGame,
+                        MainMenuSelection::LoadGame => newselection = MainMenuSelection::SeededGame,
+                        MainMenuSelection::RunHistory => newselection = MainMenuSelection::LoadGame,
+                        MainMenuSelection::Quit => newselection = MainMenuSelection::RunHistory,
                     }
                     if newselection == MainMenuSelection::LoadGame && !save_exists {
-                        newselection = MainMenuSelection::NewGame;
+                        newselection = MainMenuSelection::SeededGame;
                     }
                     return MainMenuResult::NoSelection {
                         selected: newselection,
@@ -124,12 +194,14 @@ pub fn main_menu(gs: &State, ctx: &mut Rltk) -> MainMenuResult {
                 VirtualKeyCode::Down => {
                     let mut newselection;
                     match selection {
-                        MainMenuSelection::NewGame => newselection = MainMenuSelection::LoadGame,
-                        MainMenuSelection::LoadGame => newselection = MainMenuSelection::Quit,
+                        MainMenuSelection::NewGame => newselection = MainMenuSelection::SeededGame,
+                        MainMenuSelection::SeededGame => newselection = MainMenuSelection::LoadGame,
+                        MainMenuSelection::LoadGame => newselection = MainMenuSelection::RunHistory,
+                        MainMenuSelection::RunHistory => newselection = MainMenuSelection::Quit,
                         MainMenuSelection::Quit => newselection = MainMenuSelection::NewGame,
                     }
                     if newselection == MainMenuSelection::LoadGame && !save_exists {
-                        newselection = MainMenuSelection::Quit;
+                        newselection = MainMenuSelection::RunHistory;
                     }
                     return MainMenuResult::NoSelection {
                         selected: newselection,
@@ -153,3 +225,175 @@ pub fn main_menu(gs: &State, ctx: &mut Rltk) -> MainMenuResult {
         selected: MainMenuSelection::NewGame,
     }
 }
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum SaveSlotResult {
+    NoResponse { selection: usize },
+    Cancel,
+    Selected { slot: usize },
+}
+
+// Days since 1970-01-01 to a calendar date, from Howard Hinnant's `civil_from_days`.
+pub const fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn format_timestamp(secs: u64) -> String {
+    let secs = secs as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let minutes = secs.rem_euclid(86400) / 60;
+    format!(
+        "{year}-{month:02}-{day:02} {:02}:{:02} UTC",
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+fn describe_slot(slot: usize, header: Option<&SaveHeader>) -> (String, String) {
+    let label = if slot == AUTOSAVE_SLOT {
+        "Autosave:".to_string()
+    } else {
+        format!("{}.", slot + 1)
+    };
+    let Some(header) = header else {
+        return (format!("{label} Empty"), String::new());
+    };
+    let summary = &header.summary;
+    if summary.saved_at == 0 {
+        return (
+            format!("{label} A game saved by an older version"),
+            String::new(),
+        );
+    }
+    let played = summary.play_time_secs;
+    (
+        format!(
+            "{label} Level {} - {} (depth {})",
+            summary.level, summary.map_name, summary.depth
+        ),
+        format!(
+            "   {} turns, {}:{:02}:{:02} played, saved {}",
+            summary.turns,
+            played / 3600,
+            played / 60 % 60,
+            played % 60,
+            format_timestamp(summary.saved_at)
+        ),
+    )
+}
+
+// Lists the save slots. Loading skips the empty ones and offers the autosave as well, saving can
+// use any slot but the autosave.
+pub fn save_slot_menu(
+    gs: &State,
+    ctx: &mut Rltk,
+    selection: usize,
+    saving: bool,
+) -> SaveSlotResult {
+    let mut draw_batch = DrawBatch::new();
+    if !saving {
+        let assets = gs.ecs.fetch::<RexAssets>();
+        ctx.render_xp_sprite(&assets.menu, 0, 0);
+    }
+
+    let slots = if saving {
+        SAVE_SLOTS
+    } else {
+        AUTOSAVE_SLOT + 1
+    };
+    let height = slots as i32 * 3 + 6;
+    let top = 30 - height / 2;
+    draw_batch.draw_double_box(
+        Rect::with_size(8, top, 63, height),
+        ColorPair::new(RGB::named(rltk::WHEAT), RGB::named(rltk::BLACK)),
+    );
+    draw_batch.fill_region(
+        Rect::with_size(9, top + 1, 62, height - 1),
+        ColorPair::new(RGB::named(rltk::WHITE), RGB::named(rltk::BLACK)),
+        to_cp437(' '),
+    );
+    draw_batch.print_color_centered(
+        top + 2,
+        if saving { "Save Game" } else { "Load Game" },
+        ColorPair::new(RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK)),
+    );
+    draw_batch.print_color_centered(
+        top + 3,
+        "Use Up/Down Arrows and Enter, ESCAPE to cancel",
+        ColorPair::new(RGB::named(rltk::GRAY), RGB::named(rltk::BLACK)),
+    );
+
+    let usable = |slot: usize| saving || gs.save_slots.get(slot).is_some_and(Option::is_some);
+    let mut y = top + 5;
+    for slot in 0..slots {
+        let (title, details) =
+            describe_slot(slot, gs.save_slots.get(slot).and_then(Option::as_ref));
+        let color = if slot == selection {
+            RGB::named(rltk::MAGENTA)
+        } else if usable(slot) {
+            RGB::named(rltk::WHITE)
+        } else {
+            RGB::named(rltk::GRAY)
+        };
+        draw_batch.print_color(
+            Point::new(10, y),
+            title,
+            ColorPair::new(color, RGB::named(rltk::BLACK)),
+        );
+        draw_batch.print_color(
+            Point::new(10, y + 1),
+            details,
+            ColorPair::new(RGB::named(rltk::GRAY), RGB::named(rltk::BLACK)),
+        );
+        y += 3;
+    }
+
+    if let Some(message) = &gs.menu_message {
+        draw_batch.print_color_centered(
+            top + height + 2,
+            message,
+            ColorPair::new(RGB::named(rltk::RED), RGB::named(rltk::BLACK)),
+        );
+    }
+
+    let _result = draw_batch.submit(6000);
+
+    // Steps through the slots in either direction, wrapping and skipping any that can't be used
+    let step = |forward: bool| {
+        let mut slot = selection;
+        for _ in 0..slots {
+            slot = if forward {
+                (slot + 1) % slots
+            } else {
+                (slot + slots - 1) % slots
+            };
+            if usable(slot) {
+                return slot;
+            }
+        }
+        selection
+    };
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => SaveSlotResult::Cancel,
+        Some(VirtualKeyCode::Up) => SaveSlotResult::NoResponse {
+            selection: step(false),
+        },
+        Some(VirtualKeyCode::Down) => SaveSlotResult::NoResponse {
+            selection: step(true),
+        },
+        Some(VirtualKeyCode::Return) if usable(selection) => {
+            SaveSlotResult::Selected { slot: selection }
+        }
+        _ => SaveSlotResult::NoResponse { selection },
+    }
+}
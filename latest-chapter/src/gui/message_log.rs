@@ -0,0 +1,190 @@
+use crate::{gamelog, State};
+use rltk::prelude::*;
+
+const LEFT: i32 = 2;
+const TOP: i32 = 2;
+const WIDTH: i32 = 75;
+const HEIGHT: i32 = 55;
+const VISIBLE_ROWS: usize = 48;
+const TEXT_WIDTH: usize = WIDTH as usize - 3;
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum MessageLogResult {
+    NoResponse { scroll: usize, searching: bool },
+    Typed { letter: char },
+    Erase,
+    ClearSearch,
+    Close,
+}
+
+const fn search_letter(key: VirtualKeyCode) -> Option<char> {
+    let letter = match key {
+        VirtualKeyCode::A => 'a',
+        VirtualKeyCode::B => 'b',
+        VirtualKeyCode::C => 'c',
+        VirtualKeyCode::D => 'd',
+        VirtualKeyCode::E => 'e',
+        VirtualKeyCode::F => 'f',
+        VirtualKeyCode::G => 'g',
+        VirtualKeyCode::H => 'h',
+        VirtualKeyCode::I => 'i',
+        VirtualKeyCode::J => 'j',
+        VirtualKeyCode::K => 'k',
+        VirtualKeyCode::L => 'l',
+        VirtualKeyCode::M => 'm',
+        VirtualKeyCode::N => 'n',
+        VirtualKeyCode::O => 'o',
+        VirtualKeyCode::P => 'p',
+        VirtualKeyCode::Q => 'q',
+        VirtualKeyCode::R => 'r',
+        VirtualKeyCode::S => 's',
+        VirtualKeyCode::T => 't',
+        VirtualKeyCode::U => 'u',
+        VirtualKeyCode::V => 'v',
+        VirtualKeyCode::W => 'w',
+        VirtualKeyCode::X => 'x',
+        VirtualKeyCode::Y => 'y',
+        VirtualKeyCode::Z => 'z',
+        VirtualKeyCode::Key0 => '0',
+        VirtualKeyCode::Key1 => '1',
+        VirtualKeyCode::Key2 => '2',
+        VirtualKeyCode::Key3 => '3',
+        VirtualKeyCode::Key4 => '4',
+        VirtualKeyCode::Key5 => '5',
+        VirtualKeyCode::Key6 => '6',
+        VirtualKeyCode::Key7 => '7',
+        VirtualKeyCode::Key8 => '8',
+        VirtualKeyCode::Key9 => '9',
+        VirtualKeyCode::Space => ' ',
+        VirtualKeyCode::Minus => '-',
+        VirtualKeyCode::Apostrophe => '\'',
+        _ => return None,
+    };
+    Some(letter)
+}
+
+// The whole log, newest at the bottom. `scroll` counts lines back from the newest, and only
+// messages containing the search text (ignoring case) are shown.
+pub fn show_message_log(
+    gs: &State,
+    ctx: &Rltk,
+    scroll: usize,
+    searching: bool,
+) -> MessageLogResult {
+    let mut draw_batch = DrawBatch::new();
+    let black = RGB::named(rltk::BLACK);
+    draw_batch.draw_double_box(
+        Rect::with_size(LEFT, TOP, WIDTH, HEIGHT),
+        ColorPair::new(RGB::named(rltk::WHEAT), black),
+    );
+    draw_batch.fill_region(
+        Rect::with_size(LEFT + 1, TOP + 1, WIDTH - 1, HEIGHT - 1),
+        ColorPair::new(RGB::named(rltk::WHITE), black),
+        to_cp437(' '),
+    );
+    draw_batch.print_color_centered(
+        TOP,
+        " Message History ",
+        ColorPair::new(RGB::named(rltk::YELLOW), black),
+    );
+
+    let search = gs.log_search.to_lowercase();
+    let lines: Vec<_> = gamelog::log_entries()
+        .into_iter()
+        .filter(|entry| {
+            search.is_empty()
+                || entry
+                    .iter()
+                    .any(|frag| frag.text.to_lowercase().contains(&search))
+        })
+        .flat_map(|entry| gamelog::wrap_fragments(&entry, TEXT_WIDTH))
+        .collect();
+    let scroll = scroll.min(lines.len().saturating_sub(VISIBLE_ROWS));
+    let last = lines.len() - scroll;
+    let first = last.saturating_sub(VISIBLE_ROWS);
+    for (row, line) in lines[first..last].iter().enumerate() {
+        let y = TOP + 2 + row as i32;
+        let mut x = LEFT + 2;
+        for frag in line {
+            draw_batch.print_color(
+                Point::new(x, y),
+                &frag.text,
+                ColorPair::new(frag.color, black),
+            );
+            x += frag.text.chars().count() as i32;
+        }
+    }
+    if lines.is_empty() {
+        draw_batch.print_color_centered(
+            TOP + 2,
+            "No messages match.",
+            ColorPair::new(RGB::named(rltk::GRAY), black),
+        );
+    }
+
+    let search_color = if searching {
+        RGB::named(rltk::MAGENTA)
+    } else {
+        RGB::named(rltk::CYAN)
+    };
+    draw_batch.print_color(
+        Point::new(LEFT + 2, TOP + HEIGHT - 4),
+        format!(
+            "Search: {}{}",
+            gs.log_search,
+            if searching { "_" } else { "" }
+        ),
+        ColorPair::new(search_color, black),
+    );
+    draw_batch.print_color(
+        Point::new(LEFT + 2, TOP + HEIGHT - 2),
+        if searching {
+            "Type to search, Enter to finish, ESCAPE to clear"
+        } else {
+            "Up/Down, Page Up/Down, Home/End to scroll, / to search, ESCAPE to close"
+        },
+        ColorPair::new(RGB::named(rltk::GRAY), black),
+    );
+
+    let _result = draw_batch.submit(6000);
+
+    let Some(key) = ctx.key else {
+        return MessageLogResult::NoResponse { scroll, searching };
+    };
+    if searching {
+        if let Some(letter) = search_letter(key) {
+            return MessageLogResult::Typed { letter };
+        }
+        return match key {
+            VirtualKeyCode::Back => MessageLogResult::Erase,
+            VirtualKeyCode::Escape => MessageLogResult::ClearSearch,
+            VirtualKeyCode::Return => MessageLogResult::NoResponse {
+                scroll,
+                searching: false,
+            },
+            _ => MessageLogResult::NoResponse { scroll, searching },
+        };
+    }
+
+    let page = VISIBLE_ROWS - 1;
+    let scroll = match key {
+        VirtualKeyCode::Escape => return MessageLogResult::Close,
+        VirtualKeyCode::Slash => {
+            return MessageLogResult::NoResponse {
+                scroll,
+                searching: true,
+            }
+        }
+        VirtualKeyCode::Up | VirtualKeyCode::K => scroll + 1,
+        VirtualKeyCode::Down | VirtualKeyCode::J => scroll.saturating_sub(1),
+        VirtualKeyCode::PageUp => scroll + page,
+        VirtualKeyCode::PageDown => scroll.saturating_sub(page),
+        VirtualKeyCode::Home => lines.len(),
+        VirtualKeyCode::End => 0,
+        _ => scroll,
+    };
+    MessageLogResult::NoResponse {
+        scroll: scroll.min(lines.len().saturating_sub(VISIBLE_ROWS)),
+        searching,
+    }
+}
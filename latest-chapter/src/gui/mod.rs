@@ -20,9 +20,25 @@ mod main_menu;
 pub use main_menu::*;
 mod game_over_menu;
 pub use game_over_menu::*;
+mod seed_menu;
+pub use seed_menu::*;
+mod replay_status;
+pub use replay_status::*;
 mod cheat_menu;
 pub use cheat_menu::*;
 mod vendor_menu;
 pub use vendor_menu::*;
+mod run_history_menu;
+pub use run_history_menu::*;
+mod message_log;
+pub use message_log::*;
+mod character_sheet;
+pub use character_sheet::*;
+mod keybindings_menu;
+pub use keybindings_menu::*;
+mod look;
+pub use look::*;
+mod travel_path;
+pub use travel_path::*;
 mod menus;
 pub use menus::*;
@@ -3,7 +3,7 @@ use crate::{camera, State, Viewshed};
 use rltk::prelude::*;
 use specs::prelude::*;
 
-pub fn ranged_target(gs: &State, ctx: &mut Rltk, range: i32) -> (ItemMenuResult, Option<Point>) {
+pub fn ranged_target(gs: &State, ctx: &Rltk, range: i32) -> (ItemMenuResult, Option<Point>) {
     let (min_x, max_x, min_y, max_y) = camera::get_screen_bounds(&gs.ecs, ctx);
     let player_entity = gs.ecs.fetch::<Entity>();
     let player_pos = gs.ecs.fetch::<Point>();
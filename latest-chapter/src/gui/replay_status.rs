@@ -0,0 +1,23 @@
+use crate::replay::ReplayPlayer;
+use rltk::prelude::*;
+
+pub fn draw_replay_status(replay: &ReplayPlayer) {
+    let mut draw_batch = DrawBatch::new();
+    let status = if replay.is_finished() {
+        "finished".to_string()
+    } else if replay.paused {
+        "paused".to_string()
+    } else {
+        format!("{:.0} ms/command", replay.delay_ms)
+    };
+    draw_batch.print_color(
+        Point::new(1, 44),
+        format!(
+            "Replay: turn {}/{} ({status}) - SPACE pause, . step, +/- speed, ESC quit",
+            replay.turn(),
+            replay.total_turns()
+        ),
+        ColorPair::new(RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK)),
+    );
+    let _result = draw_batch.submit(5500);
+}
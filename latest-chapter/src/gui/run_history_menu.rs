@@ -0,0 +1,164 @@
+use super::format_timestamp;
+use crate::{rex_assets::RexAssets, State};
+use rltk::prelude::*;
+
+const TOP: i32 = 6;
+const HEIGHT: i32 = 48;
+const VISIBLE_ROWS: usize = 36;
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum RunHistoryResult {
+    NoResponse { selection: usize },
+    Cancel,
+    ChangeSort,
+    Selected { selection: usize },
+}
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum MorgueViewResult {
+    NoResponse { scroll: usize },
+    Close,
+}
+
+fn draw_frame(gs: &State, ctx: &mut Rltk, draw_batch: &mut DrawBatch, title: &str, help: &str) {
+    let assets = gs.ecs.fetch::<RexAssets>();
+    ctx.render_xp_sprite(&assets.menu, 0, 0);
+    draw_batch.draw_double_box(
+        Rect::with_size(2, TOP, 75, HEIGHT),
+        ColorPair::new(RGB::named(rltk::WHEAT), RGB::named(rltk::BLACK)),
+    );
+    draw_batch.fill_region(
+        Rect::with_size(3, TOP + 1, 74, HEIGHT - 1),
+        ColorPair::new(RGB::named(rltk::WHITE), RGB::named(rltk::BLACK)),
+        to_cp437(' '),
+    );
+    draw_batch.print_color_centered(
+        TOP + 2,
+        title,
+        ColorPair::new(RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK)),
+    );
+    draw_batch.print_color_centered(
+        TOP + 3,
+        help,
+        ColorPair::new(RGB::named(rltk::GRAY), RGB::named(rltk::BLACK)),
+    );
+}
+
+// Keeps the selected row on screen once there are more runs than fit.
+const fn first_visible(selection: usize, count: usize) -> usize {
+    if count <= VISIBLE_ROWS || selection < VISIBLE_ROWS / 2 {
+        0
+    } else if selection + VISIBLE_ROWS / 2 >= count {
+        count - VISIBLE_ROWS
+    } else {
+        selection - VISIBLE_ROWS / 2
+    }
+}
+
+pub fn run_history_menu(gs: &State, ctx: &mut Rltk, selection: usize) -> RunHistoryResult {
+    let mut draw_batch = DrawBatch::new();
+    draw_frame(
+        gs,
+        ctx,
+        &mut draw_batch,
+        &format!("Past Runs, by {}", gs.run_sort.label()),
+        "Up/Down to choose, Enter for details, S to sort, ESCAPE to go back",
+    );
+
+    let runs = &gs.run_history;
+    if runs.is_empty() {
+        draw_batch.print_color_centered(
+            TOP + 6,
+            "No runs have ended yet.",
+            ColorPair::new(RGB::named(rltk::WHITE), RGB::named(rltk::BLACK)),
+        );
+    } else {
+        draw_batch.print_color(
+            Point::new(4, TOP + 5),
+            format!(
+                "{:>7} {:>5} {:>4} {:>5}  {:<20}  Killed by",
+                "Score", "Depth", "Lvl", "Kills", "Ended"
+            ),
+            ColorPair::new(RGB::named(rltk::CYAN), RGB::named(rltk::BLACK)),
+        );
+        let first = first_visible(selection, runs.len());
+        for (i, run) in runs.iter().enumerate().skip(first).take(VISIBLE_ROWS) {
+            let color = if i == selection {
+                RGB::named(rltk::MAGENTA)
+            } else {
+                RGB::named(rltk::WHITE)
+            };
+            let mut line = format!(
+                "{:>7} {:>5} {:>4} {:>5}  {:<20}  {}",
+                run.score,
+                run.depth,
+                run.level,
+                run.kills,
+                format_timestamp(run.ended_at),
+                run.killer.as_deref().unwrap_or("unknown causes")
+            );
+            line.truncate(72);
+            draw_batch.print_color(
+                Point::new(4, TOP + 7 + (i - first) as i32),
+                line,
+                ColorPair::new(color, RGB::named(rltk::BLACK)),
+            );
+        }
+    }
+
+    let _result = draw_batch.submit(6000);
+
+    let count = runs.len();
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => RunHistoryResult::Cancel,
+        Some(VirtualKeyCode::S) => RunHistoryResult::ChangeSort,
+        Some(VirtualKeyCode::Up) if count > 0 => RunHistoryResult::NoResponse {
+            selection: (selection + count - 1) % count,
+        },
+        Some(VirtualKeyCode::Down) if count > 0 => RunHistoryResult::NoResponse {
+            selection: (selection + 1) % count,
+        },
+        Some(VirtualKeyCode::Return) if selection < count => {
+            RunHistoryResult::Selected { selection }
+        }
+        _ => RunHistoryResult::NoResponse { selection },
+    }
+}
+
+pub fn morgue_view(gs: &State, ctx: &mut Rltk, scroll: usize) -> MorgueViewResult {
+    let mut draw_batch = DrawBatch::new();
+    draw_frame(
+        gs,
+        ctx,
+        &mut draw_batch,
+        "Run Details",
+        "Up/Down and Page Up/Down to scroll, ESCAPE to go back",
+    );
+
+    let lines = &gs.morgue_lines;
+    let rows = VISIBLE_ROWS + 2;
+    for (y, line) in lines.iter().skip(scroll).take(rows).enumerate() {
+        let mut line = line.clone();
+        line.truncate(72);
+        draw_batch.print_color(
+            Point::new(4, TOP + 5 + y as i32),
+            line,
+            ColorPair::new(RGB::named(rltk::WHITE), RGB::named(rltk::BLACK)),
+        );
+    }
+
+    let _result = draw_batch.submit(6000);
+
+    let last = lines.len().saturating_sub(rows);
+    let scroll = match ctx.key {
+        Some(VirtualKeyCode::Escape) => return MorgueViewResult::Close,
+        Some(VirtualKeyCode::Up) => scroll.saturating_sub(1),
+        Some(VirtualKeyCode::Down) => scroll + 1,
+        Some(VirtualKeyCode::PageUp) => scroll.saturating_sub(rows),
+        Some(VirtualKeyCode::PageDown) => scroll + rows,
+        _ => scroll,
+    };
+    MorgueViewResult::NoResponse {
+        scroll: scroll.min(last),
+    }
+}
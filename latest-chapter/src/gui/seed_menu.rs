@@ -0,0 +1,67 @@
+use crate::{rex_assets::RexAssets, State};
+use rltk::prelude::*;
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum SeedMenuResult {
+    NoResponse { seed: u64 },
+    Cancel,
+    Selected { seed: u64 },
+}
+
+pub fn seed_entry_menu(gs: &State, ctx: &mut Rltk, seed: u64) -> SeedMenuResult {
+    let mut draw_batch = DrawBatch::new();
+    let assets = gs.ecs.fetch::<RexAssets>();
+    ctx.render_xp_sprite(&assets.menu, 0, 0);
+
+    draw_batch.draw_double_box(
+        Rect::with_size(24, 18, 31, 8),
+        ColorPair::new(RGB::named(rltk::WHEAT), RGB::named(rltk::BLACK)),
+    );
+    draw_batch.print_color_centered(
+        20,
+        "Enter a world seed",
+        ColorPair::new(RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK)),
+    );
+    draw_batch.print_color_centered(
+        22,
+        format!("{seed}"),
+        ColorPair::new(RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK)),
+    );
+    draw_batch.print_color_centered(
+        24,
+        "Enter to begin, ESCAPE to cancel",
+        ColorPair::new(RGB::named(rltk::GRAY), RGB::named(rltk::BLACK)),
+    );
+
+    let _result = draw_batch.submit(6000);
+
+    ctx.key.map_or(SeedMenuResult::NoResponse { seed }, |key| {
+        let digit = match key {
+            VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some(0),
+            VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some(1),
+            VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some(2),
+            VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some(3),
+            VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some(4),
+            VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some(5),
+            VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some(6),
+            VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some(7),
+            VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some(8),
+            VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some(9),
+            _ => None,
+        };
+        if let Some(digit) = digit {
+            let seed = seed
+                .checked_mul(10)
+                .and_then(|s| s.checked_add(digit))
+                .unwrap_or(seed);
+            return SeedMenuResult::NoResponse { seed };
+        }
+
+        match key {
+            VirtualKeyCode::Back => SeedMenuResult::NoResponse { seed: seed / 10 },
+            VirtualKeyCode::Return => SeedMenuResult::Selected { seed },
+            VirtualKeyCode::Escape => SeedMenuResult::Cancel,
+            _ => SeedMenuResult::NoResponse { seed },
+        }
+    })
+}
@@ -50,7 +50,7 @@ impl Tooltip {
     }
 }
 
-pub fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
+pub fn draw_tooltips(ecs: &World, ctx: &Rltk) {
     let mut draw_batch = DrawBatch::new();
 
     let (min_x, _max_x, min_y, _max_y) = camera::get_screen_bounds(ecs, ctx);
@@ -0,0 +1,27 @@
+use crate::{camera, travel, Map, State};
+use rltk::prelude::*;
+
+// Shows the route the player is walking, or where a click would take them.
+pub fn draw_travel_path(gs: &State, ctx: &Rltk) {
+    let (path, color) = if let Some(travel) = &gs.travel {
+        (travel.path.clone(), RGB::named(rltk::DARK_GREEN))
+    } else if let Some(path) =
+        travel::mouse_tile(&gs.ecs, ctx).and_then(|tile| travel::plan_path(&gs.ecs, tile))
+    {
+        (path, RGB::named(rltk::DARKSLATEGRAY))
+    } else {
+        return;
+    };
+
+    let (min_x, max_x, min_y, max_y) = camera::get_screen_bounds(&gs.ecs, ctx);
+    let map = gs.ecs.fetch::<Map>();
+    let mut draw_batch = DrawBatch::new();
+    for idx in path {
+        let screen_x = idx as i32 % map.width - min_x + 1;
+        let screen_y = idx as i32 / map.width - min_y + 1;
+        if screen_x > 0 && screen_x <= max_x - min_x && screen_y > 0 && screen_y <= max_y - min_y {
+            draw_batch.set_bg(Point::new(screen_x, screen_y), color);
+        }
+    }
+    let _result = draw_batch.submit(4000);
+}
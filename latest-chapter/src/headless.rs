@@ -0,0 +1,407 @@
+use super::{command::Command, damage_system, Map, RunState, State};
+use specs::prelude::*;
+use std::fs;
+
+pub struct Simulation {
+    pub state: State,
+}
+
+impl Simulation {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        let mut state = State::new();
+        state.new_game(seed);
+        let mut sim = Self { state };
+        sim.advance(RunState::PreRun);
+        sim
+    }
+
+    #[must_use]
+    pub fn runstate(&self) -> RunState {
+        *self.state.ecs.fetch::<RunState>()
+    }
+
+    #[must_use]
+    pub fn is_game_over(&self) -> bool {
+        self.runstate() == RunState::GameOver
+    }
+
+    #[must_use]
+    pub fn depth(&self) -> i32 {
+        self.state.ecs.fetch::<Map>().depth
+    }
+
+    #[must_use]
+    pub const fn turns(&self) -> u32 {
+        self.state.turns
+    }
+
+    // What a test needs to tell two games apart: the level, where everything stands, the
+    // player's pools and the turn count.
+    #[cfg(test)]
+    #[must_use]
+    pub fn fingerprint(&self) -> serde_json::Value {
+        use super::{Name, Pools, Position};
+        let ecs = &self.state.ecs;
+        let player = *ecs.fetch::<Entity>();
+        let mut entities: Vec<(String, i32, i32)> =
+            (&ecs.read_storage::<Name>(), &ecs.read_storage::<Position>())
+                .join()
+                .map(|(name, pos)| (name.name.clone(), pos.x, pos.y))
+                .collect();
+        entities.sort();
+        serde_json::json!({
+            "tiles": ecs.fetch::<Map>().tiles,
+            "entities": entities,
+            "pools": ecs.read_storage::<Pools>().get(player),
+            "turns": self.turns(),
+        })
+    }
+
+    // A menu left open waits for the command that answers it. Anything else closes it first, as
+    // pressing ESCAPE would.
+    pub fn step(&mut self, command: Command) -> RunState {
+        let answers_menu = match self.runstate() {
+            RunState::AwaitingInput => false,
+            RunState::ShowVendor { .. } => {
+                matches!(command, Command::Buy { .. } | Command::Sell { .. })
+            }
+            RunState::ShowRemoveCurse => matches!(command, Command::RemoveCurse { .. }),
+            RunState::ShowIdentify => matches!(command, Command::Identify { .. }),
+            runstate => return runstate,
+        };
+        if !answers_menu {
+            *self.state.ecs.write_resource::<RunState>() = RunState::AwaitingInput;
+        }
+        // Autosaves come due and are written just as they are in play, so the dice keep in step
+        if self.state.autosave_due && self.runstate() == RunState::AwaitingInput {
+            self.state.autosave();
+        }
+
+        let newrunstate = self.state.perform(command);
+        self.advance(newrunstate)
+    }
+
+    // Runs every state that doesn't need the player until it is their turn again (or they die).
+    // Vendor, identify and remove curse menus stay open for the next command; the others that
+    // would normally pop up mid-turn are treated as cancelled.
+    fn advance(&mut self, mut newrunstate: RunState) -> RunState {
+        loop {
+            newrunstate = match newrunstate {
+                RunState::AwaitingInput
+                | RunState::GameOver
+                | RunState::MainMenu { .. }
+                | RunState::EnterSeed { .. }
+                | RunState::SaveGame { .. }
+                | RunState::LoadGame { .. }
+                | RunState::RunHistory { .. }
+                | RunState::ShowMorgue { .. }
+                | RunState::ShowVendor { .. }
+                | RunState::ShowRemoveCurse
+                | RunState::ShowIdentify => break,
+                RunState::MapGeneration => RunState::PreRun,
+                RunState::PreRun => {
+                    self.state.run_systems();
+                    self.state.ecs.maintain();
+                    RunState::AwaitingInput
+                }
+                RunState::Ticking => self.state.tick_until_input(),
+                RunState::NextLevel
+                | RunState::PreviousLevel
+                | RunState::TownPortal
+                | RunState::TeleportingToOtherLevel { .. } => {
+                    self.state.change_level(newrunstate);
+                    RunState::PreRun
+                }
+                RunState::MagicMapReveal { .. } => {
+                    let mut map = self.state.ecs.fetch_mut::<Map>();
+                    for v in &mut map.revealed_tiles {
+                        *v = true;
+                    }
+                    RunState::Ticking
+                }
+                RunState::ShowInventory
+                | RunState::ShowDropItem
+                | RunState::ShowTargeting { .. }
+                | RunState::ShowRemoveItem
+                | RunState::ShowCheatMenu
+                | RunState::ShowMessageLog { .. }
+                | RunState::ShowKeybindings
+                | RunState::ShowCharacterSheet
+                | RunState::ShowLook { .. } => RunState::AwaitingInput,
+            };
+
+            *self.state.ecs.write_resource::<RunState>() = newrunstate;
+            damage_system::delete_the_dead(&mut self.state.ecs);
+            newrunstate = self.runstate();
+        }
+        *self.state.ecs.write_resource::<RunState>() = newrunstate;
+        newrunstate
+    }
+}
+
+// Script lines are commands, optionally prefixed by a repeat count ("20 move 1 0").
+// Blank lines and lines starting with '#' are ignored.
+pub fn parse_script(script: &str) -> Result<Vec<Command>, String> {
+    let mut commands = Vec::new();
+    for (line_number, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (count, rest) = match line.split_once(' ') {
+            Some((n, rest)) if n.parse::<usize>().is_ok() => (n.parse::<usize>().unwrap(), rest),
+            _ => (1, line),
+        };
+        let command = rest
+            .parse::<Command>()
+            .map_err(|e| format!("Line {}: {e}", line_number + 1))?;
+        commands.extend(std::iter::repeat_n(command, count));
+    }
+    Ok(commands)
+}
+
+pub fn run_script(filename: &str, seed: u64) -> rltk::BError {
+    let script = fs::read_to_string(filename)?;
+    let commands = parse_script(&script)?;
+
+    let mut sim = Simulation::new(seed);
+    for command in commands {
+        sim.step(command);
+        if sim.is_game_over() {
+            break;
+        }
+    }
+
+    println!(
+        "Seed: {seed}, Turns: {}, Depth: {}, Game over: {}",
+        sim.turns(),
+        sim.depth(),
+        sim.is_game_over()
+    );
+    Ok(())
+}
+
+// Held by a test while it plays. The data directory is emptied when it's taken and removed when
+// it's dropped, so no test sees another's saves.
+#[cfg(test)]
+pub struct TestLock {
+    _lock: std::sync::MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+impl Drop for TestLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(crate::paths::data_dir());
+    }
+}
+
+// Games share the dice, the log, the spatial index and the data directory, so tests that play
+// one take turns.
+#[cfg(test)]
+pub fn test_lock() -> TestLock {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let lock = LOCK
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let _ = fs::remove_dir_all(crate::paths::data_dir());
+    TestLock { _lock: lock }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_script, test_lock, Simulation};
+    use crate::command::{self, Cheat, Command};
+    use crate::raws::{spawn_named_item, SpawnType, RAWS};
+    use crate::saveload_system::{save_slots, AUTOSAVE_SLOT};
+    use crate::{
+        KnownSpell, KnownSpells, MasterDungeonMap, Pools, RunState, Vendor, VendorMode,
+        AUTOSAVE_TURNS,
+    };
+    use specs::prelude::*;
+
+    #[test]
+    fn scripts_parse_every_command() {
+        let script = "# a comment\n\n3 wait\nsell 2\nbuy 0\nidentify 1\n\
+                      removecurse 0\nuse 1 10 12\n";
+        let commands = parse_script(script).expect("Unable to parse");
+        assert_eq!(
+            commands,
+            vec![
+                Command::Wait,
+                Command::Wait,
+                Command::Wait,
+                Command::Sell { slot: 2 },
+                Command::Buy { slot: 0 },
+                Command::Identify { slot: 1 },
+                Command::RemoveCurse { slot: 0 },
+                Command::UseItem {
+                    slot: 1,
+                    target: Some(rltk::Point::new(10, 12))
+                },
+            ]
+        );
+        assert!(parse_script("identify pocket").is_err());
+        assert!(parse_script("sell").is_err());
+        assert!(parse_script("dance").is_err());
+    }
+
+    #[test]
+    fn a_seeded_script_reaches_a_known_outcome() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(42);
+        for command in parse_script("10 wait\ndescend").expect("Unable to parse") {
+            sim.step(command);
+        }
+        // Trying to go down away from the stairs doesn't take a turn
+        assert_eq!(sim.turns(), 10);
+        assert_eq!(sim.depth(), 1);
+        assert!(sim.runstate() == RunState::AwaitingInput);
+
+        sim.step(Command::Cheat {
+            cheat: Cheat::TeleportToExit,
+        });
+        sim.step(Command::Descend);
+        assert_eq!(sim.depth(), 2);
+        assert!(!sim.is_game_over());
+    }
+
+    #[test]
+    fn commands_that_cant_be_carried_out_take_no_turn() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(42);
+        let player = *sim.state.ecs.fetch::<Entity>();
+        sim.state
+            .ecs
+            .write_storage::<KnownSpells>()
+            .get_mut(player)
+            .unwrap()
+            .spells
+            .push(KnownSpell {
+                display_name: "Zap".to_string(),
+                mana_cost: 1,
+            });
+        sim.state
+            .ecs
+            .write_storage::<Pools>()
+            .get_mut(player)
+            .unwrap()
+            .mana
+            .current = 0;
+
+        for command in [
+            Command::Descend,
+            Command::Ascend,
+            Command::UseItem {
+                slot: 99,
+                target: None,
+            },
+            Command::Cast {
+                slot: 0,
+                target: None,
+            },
+            Command::Identify { slot: 0 },
+        ] {
+            assert!(sim.step(command) == RunState::AwaitingInput);
+            assert_eq!(sim.turns(), 0, "{command:?} took a turn");
+        }
+    }
+
+    #[test]
+    fn identify_only_picks_from_what_the_menu_offers() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(42);
+        let player = *sim.state.ecs.fetch::<Entity>();
+        spawn_named_item(
+            &RAWS.lock().unwrap(),
+            &mut sim.state.ecs,
+            "Poison Potion",
+            SpawnType::Carried { by: player },
+        );
+        let identified = |sim: &Simulation| {
+            sim.state
+                .ecs
+                .fetch::<MasterDungeonMap>()
+                .identified_items
+                .contains("Poison Potion")
+        };
+        // Only the potion needs identifying, though the backpack holds more
+        assert_eq!(command::identifiable_items(&sim.state.ecs).len(), 1);
+        assert!(command::backpack_items(&sim.state.ecs).len() > 1);
+
+        *sim.state.ecs.write_resource::<RunState>() = RunState::ShowIdentify;
+        assert!(sim.step(Command::Identify { slot: 1 }) == RunState::ShowIdentify);
+        assert!(!identified(&sim));
+        assert_eq!(sim.turns(), 0);
+
+        sim.step(Command::Identify { slot: 0 });
+        assert!(identified(&sim));
+        assert_eq!(sim.turns(), 1);
+        assert!(command::identifiable_items(&sim.state.ecs).is_empty());
+    }
+
+    #[test]
+    fn vendors_can_be_traded_with() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(42);
+        let player = *sim.state.ecs.fetch::<Entity>();
+        let vendor = (
+            &sim.state.ecs.entities(),
+            &sim.state.ecs.read_storage::<Vendor>(),
+        )
+            .join()
+            .map(|(entity, _)| entity)
+            .next()
+            .expect("The town has no vendors");
+        let gold = |sim: &Simulation| {
+            sim.state
+                .ecs
+                .read_storage::<Pools>()
+                .get(player)
+                .unwrap()
+                .gold
+        };
+        sim.state
+            .ecs
+            .write_storage::<Pools>()
+            .get_mut(player)
+            .unwrap()
+            .gold = 1000.0;
+        let menu = RunState::ShowVendor {
+            vendor,
+            mode: VendorMode::Buy,
+        };
+        *sim.state.ecs.write_resource::<RunState>() = menu;
+
+        let carried = command::backpack_items(&sim.state.ecs).len();
+        assert!(sim.step(Command::Buy { slot: 0 }) == menu);
+        assert_eq!(command::backpack_items(&sim.state.ecs).len(), carried + 1);
+        let after_buying = gold(&sim);
+        assert!(after_buying < 1000.0);
+
+        assert!(sim.step(Command::Sell { slot: carried }) == menu);
+        assert_eq!(command::backpack_items(&sim.state.ecs).len(), carried);
+        assert!(gold(&sim) > after_buying);
+
+        // Anything else leaves the shop
+        sim.step(Command::Wait);
+        assert!(sim.runstate() == RunState::AwaitingInput);
+    }
+
+    #[test]
+    fn autosaves_every_hundred_turns() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(7);
+        sim.step(Command::Cheat {
+            cheat: Cheat::GodMode,
+        });
+        while sim.turns() <= AUTOSAVE_TURNS {
+            sim.step(Command::Wait);
+        }
+        let autosave = save_slots()[AUTOSAVE_SLOT]
+            .clone()
+            .expect("Nothing was autosaved");
+        assert_eq!(autosave.summary.turns, AUTOSAVE_TURNS);
+        assert_eq!(sim.depth(), 1);
+    }
+}
@@ -0,0 +1,411 @@
+use super::paths;
+use rltk::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const DEFAULT_BINDINGS: &str = include_str!("../keybindings.json");
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Action {
+    MoveWest,
+    MoveEast,
+    MoveNorth,
+    MoveSouth,
+    MoveNorthEast,
+    MoveNorthWest,
+    MoveSouthEast,
+    MoveSouthWest,
+    Wait,
+    Descend,
+    Ascend,
+    PickUp,
+    Inventory,
+    Drop,
+    RemoveItem,
+    CycleTarget,
+    Fire,
+    Look,
+    AutoExplore,
+    TravelDownStairs,
+    TravelUpStairs,
+    TravelLandmark,
+    CharacterSheet,
+    MessageLog,
+    Keybindings,
+    SaveMenu,
+    CheatMenu,
+    UseConsumable(i32),
+    CastSpell(i32),
+}
+
+impl Action {
+    #[must_use]
+    pub fn label(self) -> String {
+        match self {
+            Self::MoveWest => "Move west".to_string(),
+            Self::MoveEast => "Move east".to_string(),
+            Self::MoveNorth => "Move north".to_string(),
+            Self::MoveSouth => "Move south".to_string(),
+            Self::MoveNorthEast => "Move north-east".to_string(),
+            Self::MoveNorthWest => "Move north-west".to_string(),
+            Self::MoveSouthEast => "Move south-east".to_string(),
+            Self::MoveSouthWest => "Move south-west".to_string(),
+            Self::Wait => "Wait a turn".to_string(),
+            Self::Descend => "Go down stairs".to_string(),
+            Self::Ascend => "Go up stairs".to_string(),
+            Self::PickUp => "Pick up".to_string(),
+            Self::Inventory => "Inventory".to_string(),
+            Self::Drop => "Drop an item".to_string(),
+            Self::RemoveItem => "Remove equipment".to_string(),
+            Self::CycleTarget => "Cycle target".to_string(),
+            Self::Fire => "Fire at target".to_string(),
+            Self::Look => "Look around".to_string(),
+            Self::AutoExplore => "Explore".to_string(),
+            Self::TravelDownStairs => "Travel to stairs down".to_string(),
+            Self::TravelUpStairs => "Travel to stairs up".to_string(),
+            Self::TravelLandmark => "Travel to vendor or portal".to_string(),
+            Self::CharacterSheet => "Character sheet".to_string(),
+            Self::MessageLog => "Message history".to_string(),
+            Self::Keybindings => "Show key bindings".to_string(),
+            Self::SaveMenu => "Save and quit".to_string(),
+            Self::CheatMenu => "Cheat menu".to_string(),
+            Self::UseConsumable(n) => format!("Use consumable {n}"),
+            Self::CastSpell(n) => format!("Cast spell {n}"),
+        }
+    }
+}
+
+// Generates the mapping between key codes and the names used in the bindings file, which are the
+// names rltk gives them.
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+            match name {
+                $(stringify!($key) => Some(VirtualKeyCode::$key),)*
+                _ => None,
+            }
+        }
+
+        const fn key_name(key: VirtualKeyCode) -> &'static str {
+            match key {
+                $(VirtualKeyCode::$key => stringify!($key),)*
+                _ => "?",
+            }
+        }
+    };
+}
+
+key_names!(
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Escape,
+    Space,
+    Return,
+    Tab,
+    Back,
+    Period,
+    Comma,
+    Slash,
+    Backslash,
+    Semicolon,
+    Apostrophe,
+    Grave,
+    Minus,
+    Equals,
+    LBracket,
+    RBracket,
+);
+
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub struct KeyChord {
+    pub key: VirtualKeyCode,
+    pub shift: bool,
+    pub control: bool,
+}
+
+impl KeyChord {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut chord = Self {
+            key: VirtualKeyCode::Escape,
+            shift: false,
+            control: false,
+        };
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+        for modifier in parts {
+            match modifier {
+                "Shift" => chord.shift = true,
+                "Ctrl" => chord.control = true,
+                _ => return Err(format!("unknown modifier '{modifier}' in '{text}'")),
+            }
+        }
+        chord.key = key_from_name(key).ok_or_else(|| format!("unknown key '{key}' in '{text}'"))?;
+        Ok(chord)
+    }
+
+    #[must_use]
+    pub fn label(self) -> String {
+        let mut label = String::new();
+        if self.control {
+            label.push_str("Ctrl+");
+        }
+        if self.shift {
+            label.push_str("Shift+");
+        }
+        label.push_str(key_name(self.key));
+        label
+    }
+}
+
+#[derive(Deserialize)]
+struct BindingEntry {
+    action: Action,
+    keys: Vec<String>,
+}
+
+pub struct Keymap {
+    // In the order the file lists them, for the bindings screen.
+    pub bindings: Vec<(Action, Vec<KeyChord>)>,
+    // Where the bindings came from, and why the defaults are in use if the file was no good.
+    pub source: String,
+    lookup: HashMap<KeyChord, Action>,
+}
+
+impl Keymap {
+    /// Reads a bindings file: a list of actions, each with the keys that trigger it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file isn't valid JSON, names a key or modifier that doesn't exist, binds
+    /// a hotkey outside 1-9, or binds the same keys to two actions.
+    pub fn parse(data: &str, source: String) -> Result<Self, String> {
+        let entries: Vec<BindingEntry> = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        let mut keymap = Self {
+            bindings: Vec::new(),
+            source,
+            lookup: HashMap::new(),
+        };
+        for entry in entries {
+            if let Action::UseConsumable(n) | Action::CastSpell(n) = entry.action {
+                if !(1..=9).contains(&n) {
+                    return Err(format!(
+                        "{} has no hotkey; use 1 to 9",
+                        entry.action.label()
+                    ));
+                }
+            }
+            let mut chords = Vec::new();
+            for text in &entry.keys {
+                let chord = KeyChord::parse(text)?;
+                if let Some(other) = keymap.lookup.insert(chord, entry.action) {
+                    return Err(format!(
+                        "'{text}' is bound to both '{}' and '{}'",
+                        other.label(),
+                        entry.action.label()
+                    ));
+                }
+                chords.push(chord);
+            }
+            keymap.bindings.push((entry.action, chords));
+        }
+        Ok(keymap)
+    }
+
+    #[must_use]
+    pub fn defaults(source: String) -> Self {
+        Self::parse(DEFAULT_BINDINGS, source).expect("The built-in key bindings are invalid")
+    }
+
+    // Actions added since the player's file was written keep their default keys. An action the
+    // file lists with no keys stays unbound, and a default key the file has given to something
+    // else stays with it.
+    fn fill_in_defaults(mut self) -> Self {
+        let defaults = Self::defaults(String::new());
+        for (action, chords) in defaults.bindings {
+            if self.bindings.iter().any(|(bound, _)| *bound == action) {
+                continue;
+            }
+            let chords: Vec<KeyChord> = chords
+                .into_iter()
+                .filter(|chord| !self.lookup.contains_key(chord))
+                .collect();
+            for chord in &chords {
+                self.lookup.insert(*chord, action);
+            }
+            self.bindings.push((action, chords));
+        }
+        self
+    }
+
+    // Modifiers that aren't bound to anything are ignored, so a key bound without shift still
+    // works on keyboards that need shift to type it. '>' and '<' have bindings of their own:
+    // they travel to the stairs, and take them when the player is already standing there.
+    #[must_use]
+    pub fn action(&self, key: VirtualKeyCode, shift: bool, control: bool) -> Option<Action> {
+        self.lookup
+            .get(&KeyChord {
+                key,
+                shift,
+                control,
+            })
+            .or_else(|| {
+                self.lookup.get(&KeyChord {
+                    key,
+                    shift: false,
+                    control: false,
+                })
+            })
+            .copied()
+    }
+}
+
+#[must_use]
+pub fn bindings_file() -> PathBuf {
+    paths::data_dir().join("keybindings.json")
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_defaults() {}
+
+// Gives players a copy of the defaults to edit.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_defaults() {
+    let result = fs::create_dir_all(paths::data_dir())
+        .and_then(|()| fs::write(bindings_file(), DEFAULT_BINDINGS));
+    if let Err(e) = result {
+        rltk::console::log(format!("Unable to write the default key bindings: {e}"));
+    }
+}
+
+// A broken bindings file shouldn't leave the player unable to move, so any problem with it falls
+// back to the defaults.
+#[must_use]
+pub fn load_keymap() -> Keymap {
+    let file = bindings_file();
+    let data = match fs::read_to_string(&file) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            write_defaults();
+            return Keymap::defaults(file.display().to_string());
+        }
+        Err(e) => {
+            return Keymap::defaults(format!("defaults, {} can't be read: {e}", file.display()))
+        }
+    };
+    Keymap::parse(&data, file.display().to_string()).map_or_else(
+        |e| {
+            rltk::console::log(format!("Unable to use {}: {e}", file.display()));
+            Keymap::defaults(format!("defaults, {} has a problem: {e}", file.display()))
+        },
+        Keymap::fill_in_defaults,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Keymap};
+    use rltk::VirtualKeyCode;
+
+    #[test]
+    fn unlisted_actions_keep_their_default_keys() {
+        let data = r#"[
+            { "action": "MoveWest", "keys": ["A"] },
+            { "action": "Descend", "keys": [] },
+            { "action": "Wait", "keys": ["Comma"] }
+        ]"#;
+        let keymap = Keymap::parse(data, String::new())
+            .unwrap()
+            .fill_in_defaults();
+        assert_eq!(
+            keymap.action(VirtualKeyCode::A, false, false),
+            Some(Action::MoveWest)
+        );
+        assert_eq!(keymap.action(VirtualKeyCode::Left, false, false), None);
+        assert_eq!(keymap.action(VirtualKeyCode::Period, false, false), None);
+        assert_eq!(
+            keymap.action(VirtualKeyCode::Comma, false, false),
+            Some(Action::Wait)
+        );
+        assert_eq!(
+            keymap.action(VirtualKeyCode::I, false, false),
+            Some(Action::Inventory)
+        );
+        let defaults = Keymap::defaults(String::new());
+        assert_eq!(keymap.bindings.len(), defaults.bindings.len());
+    }
+}
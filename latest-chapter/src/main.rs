@@ -14,24 +14,34 @@
 extern crate serde;
 use rltk::{GameState, Point, Rltk};
 use specs::prelude::*;
-use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
+use specs::saveload::SimpleMarkerAllocator;
 
+mod achievements;
 mod components;
 pub use components::*;
 mod map;
 pub use map::*;
+mod morgue;
 mod player;
 use player::player_input;
 mod rect;
 pub use rect::Rect;
+mod command;
+use command::{Cheat, Command};
 mod damage_system;
+mod event_bus;
 mod gamelog;
 mod gamesystem;
 mod gui;
+mod headless;
+mod keybindings;
 pub mod map_builders;
+mod paths;
 pub mod random_table;
 pub mod raws;
+mod replay;
 pub mod rex_assets;
+mod run_history;
 pub mod saveload_system;
 mod spawner;
 pub use gamesystem::*;
@@ -39,9 +49,11 @@ pub mod effects;
 pub mod rng;
 pub mod spatial;
 mod systems;
+mod travel;
 
 const SHOW_MAPGEN_VISUALIZER: bool = false;
 const SHOW_FPS: bool = true;
+const AUTOSAVE_TURNS: u32 = 100;
 
 #[derive(PartialEq, Eq, Copy, Clone)]
 pub enum VendorMode {
@@ -63,7 +75,19 @@ pub enum RunState {
     MainMenu {
         menu_selection: gui::MainMenuSelection,
     },
-    SaveGame,
+    SaveGame {
+        selection: usize,
+    },
+    LoadGame {
+        selection: usize,
+    },
+    RunHistory {
+        selection: usize,
+    },
+    ShowMorgue {
+        selection: usize,
+        scroll: usize,
+    },
     NextLevel,
     PreviousLevel,
     TownPortal,
@@ -74,6 +98,18 @@ pub enum RunState {
     },
     MapGeneration,
     ShowCheatMenu,
+    ShowMessageLog {
+        scroll: usize,
+        searching: bool,
+    },
+    ShowKeybindings,
+    ShowCharacterSheet,
+    ShowLook {
+        x: i32,
+        y: i32,
+        index: usize,
+        detail: bool,
+    },
     ShowVendor {
         vendor: Entity,
         mode: VendorMode,
@@ -85,6 +121,9 @@ pub enum RunState {
     },
     ShowRemoveCurse,
     ShowIdentify,
+    EnterSeed {
+        seed: u64,
+    },
 }
 
 pub struct State {
@@ -94,13 +133,123 @@ pub struct State {
     mapgen_index: usize,
     mapgen_timer: f32,
     dispatcher: Box<dyn systems::UnifiedDispatcher + 'static>,
+    recording: Option<replay::Replay>,
+    replay: Option<replay::ReplayPlayer>,
+    menu_message: Option<String>,
+    save_slots: Vec<Option<saveload_system::SaveHeader>>,
+    slot: Option<usize>,
+    turns: u32,
+    play_time_ms: f64,
+    autosave_due: bool,
+    recovery_prompt: bool,
+    morgue_file: Option<std::path::PathBuf>,
+    run_history: Vec<run_history::RunRecord>,
+    run_sort: run_history::RunSort,
+    morgue_lines: Vec<String>,
+    log_search: String,
+    keymap: keybindings::Keymap,
+    travel: Option<travel::Travel>,
 }
 
 impl State {
+    #[allow(clippy::new_without_default)]
+    #[must_use]
+    pub fn new() -> Self {
+        raws::load_raws();
+        Self {
+            ecs: new_world(),
+            mapgen_next_state: Some(RunState::MainMenu {
+                menu_selection: gui::MainMenuSelection::NewGame,
+            }),
+            mapgen_index: 0,
+            mapgen_history: Vec::new(),
+            mapgen_timer: 0.0,
+            dispatcher: systems::build(),
+            recording: None,
+            replay: None,
+            menu_message: None,
+            save_slots: Vec::new(),
+            slot: None,
+            turns: 0,
+            play_time_ms: 0.0,
+            autosave_due: false,
+            recovery_prompt: false,
+            morgue_file: None,
+            run_history: Vec::new(),
+            run_sort: run_history::RunSort::Score,
+            morgue_lines: Vec::new(),
+            log_search: String::new(),
+            keymap: keybindings::Keymap::defaults("the built-in defaults".to_string()),
+            travel: None,
+        }
+    }
+
     fn run_systems(&mut self) {
         self.dispatcher.run_now(&mut self.ecs);
         self.ecs.maintain();
     }
+
+    fn tick_until_input(&mut self) -> RunState {
+        let mut newrunstate = RunState::Ticking;
+        let mut should_change_target = false;
+        while newrunstate == RunState::Ticking {
+            self.run_systems();
+            self.ecs.maintain();
+            match *self.ecs.fetch::<RunState>() {
+                RunState::AwaitingInput => {
+                    newrunstate = RunState::AwaitingInput;
+                    should_change_target = true;
+                }
+                RunState::MagicMapReveal { .. } => {
+                    newrunstate = RunState::MagicMapReveal { row: 0 }
+                }
+                RunState::TownPortal => newrunstate = RunState::TownPortal,
+                RunState::TeleportingToOtherLevel { x, y, depth } => {
+                    newrunstate = RunState::TeleportingToOtherLevel { x, y, depth }
+                }
+                RunState::ShowRemoveCurse => newrunstate = RunState::ShowRemoveCurse,
+                RunState::ShowIdentify => newrunstate = RunState::ShowIdentify,
+                _ => newrunstate = RunState::Ticking,
+            }
+        }
+        if should_change_target {
+            player::end_turn_targeting(&self.ecs);
+        }
+        // Checked here rather than once a frame so headless games unlock them too, as soon as
+        // the turn that earned them is over
+        if self.replay.is_none() {
+            achievements::check_unlocks();
+        }
+        newrunstate
+    }
+
+    fn change_level(&mut self, runstate: RunState) {
+        match runstate {
+            RunState::NextLevel => self.goto_level(1),
+            RunState::PreviousLevel => self.goto_level(-1),
+            RunState::TownPortal => {
+                // Spawn the portal
+                spawner::spawn_town_portal(&mut self.ecs);
+
+                // Transition
+                let map_depth = self.ecs.fetch::<Map>().depth;
+                let destination_offset = 0 - (map_depth - 1);
+                self.goto_level(destination_offset);
+            }
+            RunState::TeleportingToOtherLevel { x, y, depth } => {
+                self.goto_level(depth - 1);
+                let player_entity = self.ecs.fetch::<Entity>();
+                if let Some(pos) = self.ecs.write_storage::<Position>().get_mut(*player_entity) {
+                    pos.x = x;
+                    pos.y = y;
+                }
+                let mut ppos = self.ecs.fetch_mut::<rltk::Point>();
+                ppos.x = x;
+                ppos.y = y;
+            }
+            _ => {}
+        }
+    }
 }
 
 impl GameState for State {
@@ -116,13 +265,24 @@ impl GameState for State {
         ctx.cls();
         ctx.set_active_console(0);
         ctx.cls();
-        systems::particle_system::update_particles(&mut self.ecs, ctx);
+        systems::particle_system::update_particles(&self.ecs, ctx);
 
         match newrunstate {
-            RunState::MainMenu { .. } | RunState::GameOver => {}
+            RunState::MainMenu { .. }
+            | RunState::EnterSeed { .. }
+            | RunState::LoadGame { .. }
+            | RunState::RunHistory { .. }
+            | RunState::ShowMorgue { .. }
+            | RunState::GameOver => {}
             _ => {
+                if self.replay.is_none() {
+                    self.play_time_ms += f64::from(ctx.frame_time_ms);
+                }
                 camera::render_camera(&self.ecs, ctx);
                 gui::draw_ui(&self.ecs, ctx);
+                if let Some(replay) = &self.replay {
+                    gui::draw_replay_status(replay);
+                }
             }
         }
 
@@ -154,38 +314,26 @@ impl GameState for State {
                 self.ecs.maintain();
                 newrunstate = RunState::AwaitingInput;
             }
-            RunState::AwaitingInput => {
-                newrunstate = player_input(self, ctx);
-                if newrunstate != RunState::AwaitingInput {
-                    crate::gamelog::record_event(&"Turn", 1);
-                }
+            RunState::AwaitingInput
+            | RunState::ShowVendor { .. }
+            | RunState::ShowRemoveCurse
+            | RunState::ShowIdentify
+                if self.replay.is_some() =>
+            {
+                newrunstate = self.replay_input(ctx, newrunstate);
             }
-            RunState::Ticking => {
-                let mut should_change_target = false;
-                while newrunstate == RunState::Ticking {
-                    self.run_systems();
-                    self.ecs.maintain();
-                    match *self.ecs.fetch::<RunState>() {
-                        RunState::AwaitingInput => {
-                            newrunstate = RunState::AwaitingInput;
-                            should_change_target = true;
-                        }
-                        RunState::MagicMapReveal { .. } => {
-                            newrunstate = RunState::MagicMapReveal { row: 0 }
-                        }
-                        RunState::TownPortal => newrunstate = RunState::TownPortal,
-                        RunState::TeleportingToOtherLevel { x, y, depth } => {
-                            newrunstate = RunState::TeleportingToOtherLevel { x, y, depth }
-                        }
-                        RunState::ShowRemoveCurse => newrunstate = RunState::ShowRemoveCurse,
-                        RunState::ShowIdentify => newrunstate = RunState::ShowIdentify,
-                        _ => newrunstate = RunState::Ticking,
-                    }
-                }
-                if should_change_target {
-                    player::end_turn_targeting(&self.ecs);
+            RunState::AwaitingInput => {
+                if self.autosave_due {
+                    self.autosave();
                 }
+                gui::draw_travel_path(self, ctx);
+                newrunstate = if self.travel.is_some() {
+                    self.continue_travel(ctx)
+                } else {
+                    player_input(self, ctx)
+                };
             }
+            RunState::Ticking => newrunstate = self.tick_until_input(),
             RunState::ShowInventory => {
                 let result = gui::show_inventory(self, ctx);
                 match result.0 {
@@ -193,25 +341,18 @@ impl GameState for State {
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
-                        let is_ranged = self.ecs.read_storage::<Ranged>();
-                        let is_item_ranged = is_ranged.get(item_entity);
-                        if let Some(is_item_ranged) = is_item_ranged {
+                        let range = self
+                            .ecs
+                            .read_storage::<Ranged>()
+                            .get(item_entity)
+                            .map(|ranged| ranged.range);
+                        if let Some(range) = range {
                             newrunstate = RunState::ShowTargeting {
-                                range: is_item_ranged.range,
+                                range,
                                 item: item_entity,
                             };
-                        } else {
-                            let mut intent = self.ecs.write_storage::<WantsToUseItem>();
-                            intent
-                                .insert(
-                                    *self.ecs.fetch::<Entity>(),
-                                    WantsToUseItem {
-                                        item: item_entity,
-                                        target: None,
-                                    },
-                                )
-                                .expect("Unable to insert intent");
-                            newrunstate = RunState::Ticking;
+                        } else if let Some(slot) = command::backpack_slot(&self.ecs, item_entity) {
+                            newrunstate = self.perform(Command::UseItem { slot, target: None });
                         }
                     }
                 }
@@ -222,33 +363,90 @@ impl GameState for State {
                     gui::CheatMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::CheatMenuResult::NoResponse => {}
                     gui::CheatMenuResult::TeleportToExit => {
-                        self.goto_level(1);
-                        self.mapgen_next_state = Some(RunState::PreRun);
-                        newrunstate = RunState::MapGeneration;
+                        newrunstate = self.perform(Command::Cheat {
+                            cheat: Cheat::TeleportToExit,
+                        });
                     }
                     gui::CheatMenuResult::Heal => {
-                        let player = self.ecs.fetch::<Entity>();
-                        let mut pools = self.ecs.write_storage::<Pools>();
-                        let player_pools = pools.get_mut(*player).unwrap();
-                        player_pools.hit_points.current = player_pools.hit_points.max;
-                        newrunstate = RunState::AwaitingInput;
+                        newrunstate = self.perform(Command::Cheat { cheat: Cheat::Heal });
                     }
                     gui::CheatMenuResult::Reveal => {
-                        let mut map = self.ecs.fetch_mut::<Map>();
-                        for v in &mut map.revealed_tiles {
-                            *v = true;
-                        }
-                        newrunstate = RunState::AwaitingInput;
+                        newrunstate = self.perform(Command::Cheat {
+                            cheat: Cheat::Reveal,
+                        });
                     }
                     gui::CheatMenuResult::GodMode => {
-                        let player = self.ecs.fetch::<Entity>();
-                        let mut pools = self.ecs.write_storage::<Pools>();
-                        let player_pools = pools.get_mut(*player).unwrap();
-                        player_pools.god_mode = true;
+                        newrunstate = self.perform(Command::Cheat {
+                            cheat: Cheat::GodMode,
+                        });
+                    }
+                    gui::CheatMenuResult::ReloadRaws => {
+                        // A development aid rather than a move, so it isn't recorded for replays.
+                        reload_raws(&mut self.ecs);
                         newrunstate = RunState::AwaitingInput;
                     }
                 }
             }
+            RunState::ShowMessageLog { scroll, searching } => {
+                match gui::show_message_log(self, ctx, scroll, searching) {
+                    gui::MessageLogResult::NoResponse { scroll, searching } => {
+                        newrunstate = RunState::ShowMessageLog { scroll, searching };
+                    }
+                    gui::MessageLogResult::Typed { letter } => {
+                        self.log_search.push(letter);
+                        newrunstate = RunState::ShowMessageLog {
+                            scroll: 0,
+                            searching,
+                        };
+                    }
+                    gui::MessageLogResult::Erase => {
+                        self.log_search.pop();
+                        newrunstate = RunState::ShowMessageLog {
+                            scroll: 0,
+                            searching,
+                        };
+                    }
+                    gui::MessageLogResult::ClearSearch => {
+                        self.log_search.clear();
+                        newrunstate = RunState::ShowMessageLog {
+                            scroll: 0,
+                            searching: false,
+                        };
+                    }
+                    gui::MessageLogResult::Close => newrunstate = RunState::AwaitingInput,
+                }
+            }
+            RunState::ShowKeybindings => {
+                if gui::show_keybindings(self, ctx) == gui::KeybindingsResult::Close {
+                    newrunstate = RunState::AwaitingInput;
+                }
+            }
+            RunState::ShowCharacterSheet => {
+                if gui::show_character_sheet(self, ctx) == gui::CharacterSheetResult::Close {
+                    newrunstate = RunState::AwaitingInput;
+                }
+            }
+            RunState::ShowLook {
+                x,
+                y,
+                index,
+                detail,
+            } => match gui::show_look(self, ctx, (x, y), index, detail) {
+                gui::LookResult::NoResponse {
+                    x,
+                    y,
+                    index,
+                    detail,
+                } => {
+                    newrunstate = RunState::ShowLook {
+                        x,
+                        y,
+                        index,
+                        detail,
+                    };
+                }
+                gui::LookResult::Close => newrunstate = RunState::AwaitingInput,
+            },
             RunState::ShowDropItem => {
                 let result = gui::drop_item_menu(self, ctx);
                 match result.0 {
@@ -256,14 +454,9 @@ impl GameState for State {
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
-                        let mut intent = self.ecs.write_storage::<WantsToDropItem>();
-                        intent
-                            .insert(
-                                *self.ecs.fetch::<Entity>(),
-                                WantsToDropItem { item: item_entity },
-                            )
-                            .expect("Unable to insert intent");
-                        newrunstate = RunState::Ticking;
+                        if let Some(slot) = command::backpack_slot(&self.ecs, item_entity) {
+                            newrunstate = self.perform(Command::DropItem { slot });
+                        }
                     }
                 }
             }
@@ -274,14 +467,12 @@ impl GameState for State {
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
-                        let mut intent = self.ecs.write_storage::<WantsToRemoveItem>();
-                        intent
-                            .insert(
-                                *self.ecs.fetch::<Entity>(),
-                                WantsToRemoveItem { item: item_entity },
-                            )
-                            .expect("Unable to insert intent");
-                        newrunstate = RunState::Ticking;
+                        if let Some(slot) = command::equipped_items(&self.ecs)
+                            .iter()
+                            .position(|e| *e == item_entity)
+                        {
+                            newrunstate = self.perform(Command::RemoveItem { slot });
+                        }
                     }
                 }
             }
@@ -292,8 +483,12 @@ impl GameState for State {
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
-                        self.ecs.write_storage::<CursedItem>().remove(item_entity);
-                        newrunstate = RunState::Ticking;
+                        if let Some(slot) = command::cursed_items(&self.ecs)
+                            .iter()
+                            .position(|e| *e == item_entity)
+                        {
+                            newrunstate = self.perform(Command::RemoveCurse { slot });
+                        }
                     }
                 }
             }
@@ -304,11 +499,12 @@ impl GameState for State {
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
-                        if let Some(name) = self.ecs.read_storage::<Name>().get(item_entity) {
-                            let mut dm = self.ecs.fetch_mut::<MasterDungeonMap>();
-                            dm.identified_items.insert(name.name.clone());
+                        if let Some(slot) = command::identifiable_items(&self.ecs)
+                            .iter()
+                            .position(|e| *e == item_entity)
+                        {
+                            newrunstate = self.perform(Command::Identify { slot });
                         }
-                        newrunstate = RunState::Ticking;
                     }
                 }
             }
@@ -318,79 +514,39 @@ impl GameState for State {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
-                        if self.ecs.read_storage::<SpellTemplate>().get(item).is_some() {
-                            let mut intent = self.ecs.write_storage::<WantsToCastSpell>();
-                            intent
-                                .insert(
-                                    *self.ecs.fetch::<Entity>(),
-                                    WantsToCastSpell {
-                                        spell: item,
-                                        target: result.1,
-                                    },
-                                )
-                                .expect("Unable to insert intent");
-                            newrunstate = RunState::Ticking;
-                        } else {
-                            let mut intent = self.ecs.write_storage::<WantsToUseItem>();
-                            intent
-                                .insert(
-                                    *self.ecs.fetch::<Entity>(),
-                                    WantsToUseItem {
-                                        item,
-                                        target: result.1,
-                                    },
-                                )
-                                .expect("Unable to insert intent");
-                            newrunstate = RunState::Ticking;
-                        }
+                        let target = result.1;
+                        let command =
+                            if self.ecs.read_storage::<SpellTemplate>().get(item).is_some() {
+                                command::spell_slot(&self.ecs, item)
+                                    .map(|slot| Command::Cast { slot, target })
+                            } else {
+                                command::backpack_slot(&self.ecs, item)
+                                    .map(|slot| Command::UseItem { slot, target })
+                            };
+                        newrunstate = command
+                            .map_or(RunState::AwaitingInput, |command| self.perform(command));
                     }
                 }
             }
             RunState::ShowVendor { vendor, mode } => {
-                use crate::raws::{SpawnType, RAWS};
                 let result = gui::show_vendor_menu(self, ctx, vendor, mode);
                 match result.0 {
                     gui::VendorResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::VendorResult::NoResponse => {}
                     gui::VendorResult::Sell => {
-                        let price = self
-                            .ecs
-                            .read_storage::<Item>()
-                            .get(result.1.unwrap())
-                            .unwrap()
-                            .base_value
-                            * 0.8;
-                        self.ecs
-                            .write_storage::<Pools>()
-                            .get_mut(*self.ecs.fetch::<Entity>())
-                            .unwrap()
-                            .gold += price;
-                        self.ecs
-                            .delete_entity(result.1.unwrap())
-                            .expect("Unable to delete");
+                        if let Some(slot) = command::backpack_slot(&self.ecs, result.1.unwrap()) {
+                            newrunstate = self.perform(Command::Sell { slot });
+                        }
                     }
                     gui::VendorResult::Buy => {
                         let tag = result.2.unwrap();
-                        let price = result.3.unwrap();
-                        let mut pools = self.ecs.write_storage::<Pools>();
-                        let player_entity = self.ecs.fetch::<Entity>();
-                        let mut identified = self.ecs.write_storage::<IdentifiedItem>();
-                        identified
-                            .insert(*player_entity, IdentifiedItem { name: tag.clone() })
-                            .expect("Unable to insert");
-                        std::mem::drop(identified);
-                        let player_pools = pools.get_mut(*player_entity).unwrap();
-                        std::mem::drop(player_entity);
-                        if player_pools.gold >= price {
-                            player_pools.gold -= price;
-                            std::mem::drop(pools);
-                            let player_entity = *self.ecs.fetch::<Entity>();
-                            crate::raws::spawn_named_item(
-                                &RAWS.lock().unwrap(),
-                                &mut self.ecs,
-                                &tag,
-                                SpawnType::Carried { by: player_entity },
-                            );
+                        let slot = self.ecs.read_storage::<Vendor>().get(vendor).and_then(|v| {
+                            raws::get_vendor_items(&v.categories, &raws::RAWS.lock().unwrap())
+                                .iter()
+                                .position(|(name, _price)| *name == tag)
+                        });
+                        if let Some(slot) = slot {
+                            newrunstate = self.perform(Command::Buy { slot });
                         }
                     }
                     gui::VendorResult::BuyMode => {
@@ -415,12 +571,45 @@ impl GameState for State {
                             menu_selection: selected,
                         }
                     }
+                    gui::MainMenuResult::RecoverAutosave => {
+                        self.recovery_prompt = false;
+                        match self.load_game(saveload_system::AUTOSAVE_SLOT) {
+                            Ok(()) => newrunstate = RunState::AwaitingInput,
+                            Err(message) => {
+                                self.menu_message = Some(message);
+                                newrunstate = RunState::MainMenu {
+                                    menu_selection: gui::MainMenuSelection::LoadGame,
+                                };
+                            }
+                        }
+                    }
+                    gui::MainMenuResult::DismissRecovery { selected } => {
+                        self.recovery_prompt = false;
+                        saveload_system::set_unsaved_progress(false);
+                        newrunstate = RunState::MainMenu {
+                            menu_selection: selected,
+                        };
+                    }
                     gui::MainMenuResult::Selected { selected } => match selected {
-                        gui::MainMenuSelection::NewGame => newrunstate = RunState::PreRun,
+                        gui::MainMenuSelection::NewGame => {
+                            self.menu_message = None;
+                            self.new_game(rng::random_seed());
+                            self.mapgen_next_state = Some(RunState::PreRun);
+                            newrunstate = RunState::MapGeneration;
+                        }
+                        gui::MainMenuSelection::SeededGame => {
+                            self.menu_message = None;
+                            newrunstate = RunState::EnterSeed { seed: 0 };
+                        }
                         gui::MainMenuSelection::LoadGame => {
-                            saveload_system::load_game(&mut self.ecs);
-                            newrunstate = RunState::AwaitingInput;
-                            saveload_system::delete_save();
+                            self.menu_message = None;
+                            newrunstate = self.slot_menu(false);
+                        }
+                        gui::MainMenuSelection::RunHistory => {
+                            self.menu_message = None;
+                            self.run_history = run_history::load_runs();
+                            run_history::sort_runs(&mut self.run_history, self.run_sort);
+                            newrunstate = RunState::RunHistory { selection: 0 };
                         }
                         gui::MainMenuSelection::Quit => {
                             ::std::process::exit(0);
@@ -428,12 +617,39 @@ impl GameState for State {
                     },
                 }
             }
+            RunState::EnterSeed { seed } => {
+                let result = gui::seed_entry_menu(self, ctx, seed);
+                match result {
+                    gui::SeedMenuResult::Cancel => {
+                        newrunstate = RunState::MainMenu {
+                            menu_selection: gui::MainMenuSelection::SeededGame,
+                        }
+                    }
+                    gui::SeedMenuResult::NoResponse { seed } => {
+                        newrunstate = RunState::EnterSeed { seed };
+                    }
+                    gui::SeedMenuResult::Selected { seed } => {
+                        self.new_game(seed);
+                        self.mapgen_next_state = Some(RunState::PreRun);
+                        newrunstate = RunState::MapGeneration;
+                    }
+                }
+            }
             RunState::GameOver => {
-                let result = gui::game_over(ctx);
+                if let Some(recording) = self.recording.take() {
+                    if self.replay.is_none() {
+                        if let Err(e) = replay::save_replay(&recording, &replay::last_game_file()) {
+                            rltk::console::log(format!("Unable to save the replay: {e}"));
+                        }
+                    }
+                }
+                let result = gui::game_over(&self.ecs, ctx, self.morgue_file.as_deref());
                 match result {
                     gui::GameOverResult::NoSelection => {}
                     gui::GameOverResult::QuitToMenu => {
-                        self.game_over_cleanup();
+                        saveload_system::set_unsaved_progress(false);
+                        self.replay = None;
+                        self.morgue_file = None;
                         newrunstate = RunState::MapGeneration;
                         self.mapgen_next_state = Some(RunState::MainMenu {
                             menu_selection: gui::MainMenuSelection::NewGame,
@@ -441,43 +657,90 @@ impl GameState for State {
                     }
                 }
             }
-            RunState::SaveGame => {
-                saveload_system::save_game(&mut self.ecs);
-                newrunstate = RunState::MainMenu {
-                    menu_selection: gui::MainMenuSelection::LoadGame,
-                };
-            }
-            RunState::NextLevel => {
-                self.goto_level(1);
-                self.mapgen_next_state = Some(RunState::PreRun);
-                newrunstate = RunState::MapGeneration;
+            RunState::LoadGame { selection } => {
+                match gui::save_slot_menu(self, ctx, selection, false) {
+                    gui::SaveSlotResult::NoResponse { selection } => {
+                        newrunstate = RunState::LoadGame { selection };
+                    }
+                    gui::SaveSlotResult::Cancel => {
+                        self.menu_message = None;
+                        newrunstate = RunState::MainMenu {
+                            menu_selection: gui::MainMenuSelection::LoadGame,
+                        };
+                    }
+                    gui::SaveSlotResult::Selected { slot } => match self.load_game(slot) {
+                        Ok(()) => {
+                            self.menu_message = None;
+                            newrunstate = RunState::AwaitingInput;
+                        }
+                        Err(message) => {
+                            self.menu_message = Some(message);
+                            newrunstate = RunState::LoadGame { selection: slot };
+                        }
+                    },
+                }
             }
-            RunState::PreviousLevel => {
-                self.goto_level(-1);
-                self.mapgen_next_state = Some(RunState::PreRun);
-                newrunstate = RunState::MapGeneration;
+            RunState::RunHistory { selection } => {
+                match gui::run_history_menu(self, ctx, selection) {
+                    gui::RunHistoryResult::NoResponse { selection } => {
+                        newrunstate = RunState::RunHistory { selection };
+                    }
+                    gui::RunHistoryResult::Cancel => {
+                        newrunstate = RunState::MainMenu {
+                            menu_selection: gui::MainMenuSelection::RunHistory,
+                        };
+                    }
+                    gui::RunHistoryResult::ChangeSort => {
+                        self.run_sort = self.run_sort.next();
+                        run_history::sort_runs(&mut self.run_history, self.run_sort);
+                        newrunstate = RunState::RunHistory { selection: 0 };
+                    }
+                    gui::RunHistoryResult::Selected { selection } => {
+                        self.morgue_lines = run_history::details(&self.run_history[selection]);
+                        newrunstate = RunState::ShowMorgue {
+                            selection,
+                            scroll: 0,
+                        };
+                    }
+                }
             }
-            RunState::TownPortal => {
-                // Spawn the portal
-                spawner::spawn_town_portal(&mut self.ecs);
-
-                // Transition
-                let map_depth = self.ecs.fetch::<Map>().depth;
-                let destination_offset = 0 - (map_depth - 1);
-                self.goto_level(destination_offset);
-                self.mapgen_next_state = Some(RunState::PreRun);
-                newrunstate = RunState::MapGeneration;
+            RunState::ShowMorgue { selection, scroll } => {
+                match gui::morgue_view(self, ctx, scroll) {
+                    gui::MorgueViewResult::NoResponse { scroll } => {
+                        newrunstate = RunState::ShowMorgue { selection, scroll };
+                    }
+                    gui::MorgueViewResult::Close => {
+                        newrunstate = RunState::RunHistory { selection };
+                    }
+                }
             }
-            RunState::TeleportingToOtherLevel { x, y, depth } => {
-                self.goto_level(depth - 1);
-                let player_entity = self.ecs.fetch::<Entity>();
-                if let Some(pos) = self.ecs.write_storage::<Position>().get_mut(*player_entity) {
-                    pos.x = x;
-                    pos.y = y;
+            RunState::SaveGame { selection } => {
+                match gui::save_slot_menu(self, ctx, selection, true) {
+                    gui::SaveSlotResult::NoResponse { selection } => {
+                        newrunstate = RunState::SaveGame { selection };
+                    }
+                    gui::SaveSlotResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::SaveSlotResult::Selected { slot } => {
+                        newrunstate = match self.save_game(slot) {
+                            Ok(()) => RunState::MainMenu {
+                                menu_selection: gui::MainMenuSelection::LoadGame,
+                            },
+                            Err(e) => {
+                                gamelog::Logger::new()
+                                    .color(rltk::RED)
+                                    .append(&format!("Unable to save the game: {e}"))
+                                    .log();
+                                RunState::AwaitingInput
+                            }
+                        };
+                    }
                 }
-                let mut ppos = self.ecs.fetch_mut::<rltk::Point>();
-                ppos.x = x;
-                ppos.y = y;
+            }
+            RunState::NextLevel
+            | RunState::PreviousLevel
+            | RunState::TownPortal
+            | RunState::TeleportingToOtherLevel { .. } => {
+                self.change_level(newrunstate);
                 self.mapgen_next_state = Some(RunState::PreRun);
                 newrunstate = RunState::MapGeneration;
             }
@@ -500,6 +763,12 @@ impl GameState for State {
             *runwriter = newrunstate;
         }
         damage_system::delete_the_dead(&mut self.ecs);
+        if newrunstate != RunState::GameOver
+            && *self.ecs.fetch::<RunState>() == RunState::GameOver
+            && self.replay.is_none()
+        {
+            self.end_run();
+        }
 
         let _result = rltk::render_draw_buffer(ctx);
         if SHOW_FPS {
@@ -515,36 +784,232 @@ impl State {
         // Build a new map and place the player
         let current_depth = self.ecs.fetch::<Map>().depth;
         self.generate_world_map(current_depth + offset, offset);
+        event_bus::emit(&event_bus::GameEvent::LevelEntered {
+            depth: current_depth + offset,
+            offset,
+        });
 
         // Notify the player
         gamelog::Logger::new().append(&"You change level.").log();
+        self.autosave_due = true;
     }
 
-    fn game_over_cleanup(&mut self) {
-        // Delete everything
-        let mut to_delete = Vec::new();
-        for e in self.ecs.entities().join() {
-            to_delete.push(e);
+    // Done as the player dies, before going back to the menu throws the world away.
+    fn end_run(&mut self) {
+        let play_time_secs = (self.play_time_ms / 1000.0) as u64;
+        match morgue::write_morgue(&self.ecs, self.turns, play_time_secs) {
+            Ok(file) => self.morgue_file = Some(file),
+            Err(e) => rltk::console::log(format!("Unable to write the morgue file: {e}")),
         }
-        for del in &to_delete {
-            self.ecs.delete_entity(*del).expect("Deletion failed");
+        let run = run_history::finished_run(&self.ecs, self.turns, self.morgue_file.clone());
+        if let Err(e) = run_history::add_run(run) {
+            rltk::console::log(format!("Unable to record the run: {e}"));
         }
+    }
 
-        // Spawn a new player
-        {
-            let player_entity = spawner::player(&mut self.ecs, 0, 0);
-            let mut player_entity_writer = self.ecs.write_resource::<Entity>();
-            *player_entity_writer = player_entity;
-        }
+    fn new_game(&mut self, seed: u64) {
+        rng::reseed(seed);
+        self.ecs = new_world();
+        self.ecs.insert(map::MasterDungeonMap::new(seed));
+        self.recording = Some(replay::Replay::new(seed));
+        self.replay = None;
+        self.slot = None;
+        self.turns = 0;
+        self.play_time_ms = 0.0;
+        self.autosave_due = false;
 
-        // Replace the world maps
-        self.ecs.insert(map::MasterDungeonMap::new());
+        gamelog::clear_log();
+        gamelog::Logger::new()
+            .append(&"Welcome to")
+            .color(rltk::CYAN)
+            .append(&"Rusty Roguelike")
+            .log();
+        gamelog::clear_events();
 
         // Build a new map and place the player
         self.generate_world_map(1, 0);
     }
 
+    // Reads the slot headers once, rather than every frame the menu is open.
+    fn slot_menu(&mut self, saving: bool) -> RunState {
+        self.save_slots = saveload_system::save_slots();
+        if saving {
+            RunState::SaveGame {
+                selection: self.slot.unwrap_or(0),
+            }
+        } else {
+            RunState::LoadGame {
+                selection: self
+                    .save_slots
+                    .iter()
+                    .position(Option::is_some)
+                    .unwrap_or(0),
+            }
+        }
+    }
+
+    // The recording is only given up once the save is safely written, so a failed save leaves
+    // the game as it was.
+    fn save_game(&mut self, slot: usize) -> std::io::Result<()> {
+        saveload_system::save_game(
+            &mut self.ecs,
+            slot,
+            self.turns,
+            (self.play_time_ms / 1000.0) as u64,
+        )?;
+        // A replay left over from whatever was in the slot before would no longer match
+        let replay_file = saveload_system::slot_replay_file(slot);
+        let result = self.recording.take().map_or_else(
+            || replay::delete_replay(&replay_file),
+            |recording| replay::save_replay(&recording, &replay_file),
+        );
+        if let Err(e) = result {
+            rltk::console::log(format!("Unable to save the replay: {e}"));
+        }
+        saveload_system::set_unsaved_progress(false);
+        Ok(())
+    }
+
+    // Recorded as a reload, which reseeds the dice. That is deliberate: dice aren't saved, so
+    // carrying on from here has to roll exactly what loading the autosave later would.
+    fn autosave(&mut self) {
+        self.autosave_due = false;
+        let saved = saveload_system::autosave(
+            &mut self.ecs,
+            self.turns,
+            (self.play_time_ms / 1000.0) as u64,
+        );
+        let result = saved.and_then(|()| {
+            let replay_file = saveload_system::slot_replay_file(saveload_system::AUTOSAVE_SLOT);
+            self.recording.as_ref().map_or_else(
+                || replay::delete_replay(&replay_file),
+                |recording| replay::save_replay(recording, &replay_file),
+            )
+        });
+        if let Err(e) = result {
+            gamelog::Logger::new()
+                .color(rltk::RED)
+                .append(&format!("Unable to autosave: {e}"))
+                .log();
+        }
+        *self.ecs.write_resource::<RunState>() = RunState::AwaitingInput;
+        self.record(Command::Reload, RunState::AwaitingInput);
+    }
+
+    // A save that can't be loaded is left on disk, and the reason is shown on the load screen.
+    fn load_game(&mut self, slot: usize) -> Result<(), String> {
+        self.ecs = new_world();
+        let header = match saveload_system::load_game(&mut self.ecs, slot) {
+            Ok(header) => header,
+            Err(message) => {
+                self.ecs = new_world();
+                return Err(message);
+            }
+        };
+        self.slot = Some(slot).filter(|&slot| slot != saveload_system::AUTOSAVE_SLOT);
+        self.travel = None;
+        self.turns = header.summary.turns;
+        self.play_time_ms = header.summary.play_time_secs as f64 * 1000.0;
+
+        // Saves from before raws were hashed have a hash of 0, and there's nothing to compare.
+        if header.raws_hash != 0 && header.raws_hash != raws::raws_hash() {
+            gamelog::Logger::new()
+                .color(rltk::YELLOW)
+                .append(&"This save was made with different game data, so some things may have changed.")
+                .log();
+        }
+
+        // Keep recording only if the replay picks up exactly where this save left off. Saves from
+        // before slots don't know their turn count, so only the seed can be checked.
+        let seed = self.ecs.fetch::<MasterDungeonMap>().seed;
+        let legacy = header.summary.saved_at == 0;
+        self.recording =
+            replay::saved_replay(&saveload_system::slot_replay_file(slot)).filter(|recording| {
+                recording.seed == seed && (legacy || recording.turn == header.summary.turns)
+            });
+        self.record(Command::Reload, RunState::AwaitingInput);
+        Ok(())
+    }
+
+    fn start_replay(&mut self, recording: &replay::Replay) {
+        self.new_game(recording.seed);
+        self.replay = Some(replay::ReplayPlayer::new(recording));
+        self.mapgen_next_state = Some(RunState::PreRun);
+    }
+
+    fn replay_input(&mut self, ctx: &Rltk, runstate: RunState) -> RunState {
+        if ctx.key == Some(rltk::VirtualKeyCode::Escape) {
+            self.replay = None;
+            self.recording = None;
+            return RunState::MainMenu {
+                menu_selection: gui::MainMenuSelection::NewGame,
+            };
+        }
+        let command = self
+            .replay
+            .as_mut()
+            .and_then(|replay| replay.update(ctx.key, ctx.frame_time_ms));
+        command.map_or(runstate, |command| self.perform(command))
+    }
+
+    // Takes the next step of a walk. Pressing a key or clicking stops it.
+    fn continue_travel(&mut self, ctx: &Rltk) -> RunState {
+        if ctx.key.is_some() || ctx.left_click {
+            self.travel = None;
+            return RunState::AwaitingInput;
+        }
+        let Some(command) = self
+            .travel
+            .as_mut()
+            .and_then(|travel| travel.next_step(&self.ecs))
+        else {
+            self.travel = None;
+            return RunState::AwaitingInput;
+        };
+        // Bumping into a wall or a vendor ends the walk
+        let newrunstate = self.perform(command);
+        if newrunstate != RunState::Ticking {
+            self.travel = None;
+        }
+        newrunstate
+    }
+
+    pub fn perform(&mut self, command: Command) -> RunState {
+        // Reloading must see the world exactly as it was saved, particles and all
+        if command != Command::Reload {
+            systems::particle_system::cull_particles(&mut self.ecs);
+        }
+        let runstate = *self.ecs.fetch::<RunState>();
+        let newrunstate = command::apply(&mut self.ecs, command, runstate);
+        self.record(command, newrunstate);
+        newrunstate
+    }
+
+    fn record(&mut self, command: Command, newrunstate: RunState) {
+        let ends_turn = !matches!(
+            newrunstate,
+            RunState::AwaitingInput
+                | RunState::ShowVendor { .. }
+                | RunState::ShowIdentify
+                | RunState::ShowRemoveCurse
+        );
+        if ends_turn {
+            gamelog::record_event(&"Turn", 1);
+            self.turns += 1;
+            if self.turns.is_multiple_of(AUTOSAVE_TURNS) {
+                self.autosave_due = true;
+            }
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.record(command, ends_turn);
+            if command == Command::Reload {
+                rng::reseed(recording.reload_seed());
+            }
+        }
+    }
+
     fn generate_world_map(&mut self, new_depth: i32, offset: i32) {
+        self.travel = None;
         self.mapgen_index = 0;
         self.mapgen_timer = 0.0;
         self.mapgen_history.clear();
@@ -554,20 +1019,111 @@ impl State {
         } else {
             map::thaw_level_entities(&mut self.ecs);
         }
+    }
+}
+
+// Every game and every loaded save starts from a brand new world, so entity ids are handed out
+// the same way no matter what was played earlier in the session.
+fn new_world() -> World {
+    let mut ecs = World::new();
+    saveload_system::register_components(&mut ecs);
+    ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+
+    ecs.insert(map::MasterDungeonMap::default());
+    ecs.insert(Map::new(1, 64, 64, &"New Map"));
+    ecs.insert(Point::new(0, 0));
+    let player_entity = spawner::player(&mut ecs, 0, 0);
+    ecs.insert(player_entity);
+    ecs.insert(RunState::MapGeneration {});
+    ecs.insert(morgue::Killer::default());
+    ecs.insert(systems::particle_system::ParticleBuilder::new());
+    ecs.insert(rex_assets::RexAssets::new());
+
+    ecs
+}
+
+fn reload_raws(ecs: &mut World) {
+    match raws::reload_raws(ecs) {
+        Ok(updated) => gamelog::Logger::new()
+            .append(&format!("Raws reloaded, {updated} entities updated."))
+            .log(),
+        Err(errors) => {
+            for error in &errors {
+                rltk::console::log(error.to_string());
+            }
+            gamelog::Logger::new()
+                .color(rltk::RED)
+                .append(&format!(
+                    "Raws not reloaded: {} problem(s), see the console.",
+                    errors.len()
+                ))
+                .log();
+        }
+    }
+}
 
-        gamelog::clear_log();
-        gamelog::Logger::new()
-            .append(&"Welcome to")
-            .color(rltk::CYAN)
-            .append(&"Rusty Roguelike")
-            .log();
+fn validate_raws(path: Option<&String>) -> rltk::BError {
+    let mut files = raws::embedded_raws();
+    files.extend(raws::mod_raws());
+    if let Some(path) = path {
+        raws::read_raw_files(std::path::Path::new(path), &mut files)?;
+    }
+    let errors = raws::parse_raws(&files).err().unwrap_or_default();
 
-        gamelog::clear_events();
+    for error in &errors {
+        println!("{error}");
+    }
+    if errors.is_empty() {
+        println!("Raws OK");
+        Ok(())
+    } else {
+        Err(format!("{} problem(s) found", errors.len()).into())
     }
 }
 
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == "--validate-raws") {
+        return validate_raws(args.get(i + 1));
+    }
+    if let Some(i) = args.iter().position(|a| a == "--save-format") {
+        let encoding = args.get(i + 1).ok_or("--save-format needs a format")?;
+        saveload_system::set_save_encoding(encoding.parse()?);
+    }
+    if let Some(i) = args.iter().position(|a| a == "--event-log") {
+        let file = args.get(i + 1).ok_or("--event-log needs a file")?;
+        let sink = event_bus::JsonLinesSink::create(std::path::Path::new(file))?;
+        event_bus::add_sink(Box::new(sink));
+    }
+    if let Some(i) = args.iter().position(|a| a == "--raws-dir") {
+        let dir = args.get(i + 1).ok_or("--raws-dir needs a folder")?;
+        raws::set_raws_dir(std::path::PathBuf::from(dir));
+    }
+    if args.iter().any(|a| a == "--auto-pickup") {
+        travel::set_auto_pickup(true);
+    }
+    if args.iter().any(|a| a == "--auto-stairs") {
+        travel::set_auto_stairs(true);
+    }
+    if let Some(i) = args.iter().position(|a| a == "--headless") {
+        let script = args.get(i + 1).ok_or("--headless needs a script file")?;
+        let seed = match args.iter().position(|a| a == "--seed") {
+            Some(i) => args
+                .get(i + 1)
+                .ok_or("--seed needs a number")?
+                .parse::<u64>()?,
+            None => rng::random_seed(),
+        };
+        return headless::run_script(script, seed);
+    }
+    let recording = match args.iter().position(|a| a == "--replay") {
+        Some(i) => Some(replay::load_replay(std::path::Path::new(
+            args.get(i + 1).ok_or("--replay needs a replay file")?,
+        ))?),
+        None => None,
+    };
+
     let mut context = RltkBuilder::simple(80, 60)
         .unwrap()
         .with_title("Roguelike Tutorial")
@@ -576,112 +1132,14 @@ fn main() -> rltk::BError {
         .with_vsync(false)
         .build()?;
     context.with_post_scanlines(true);
-    let mut gs = State {
-        ecs: World::new(),
-        mapgen_next_state: Some(RunState::MainMenu {
-            menu_selection: gui::MainMenuSelection::NewGame,
-        }),
-        mapgen_index: 0,
-        mapgen_history: Vec::new(),
-        mapgen_timer: 0.0,
-        dispatcher: systems::build(),
-    };
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<Name>();
-    gs.ecs.register::<BlocksTile>();
-    gs.ecs.register::<WantsToMelee>();
-    gs.ecs.register::<Item>();
-    gs.ecs.register::<ProvidesHealing>();
-    gs.ecs.register::<InflictsDamage>();
-    gs.ecs.register::<AreaOfEffect>();
-    gs.ecs.register::<Consumable>();
-    gs.ecs.register::<Ranged>();
-    gs.ecs.register::<InBackpack>();
-    gs.ecs.register::<WantsToPickupItem>();
-    gs.ecs.register::<WantsToUseItem>();
-    gs.ecs.register::<WantsToDropItem>();
-    gs.ecs.register::<Confusion>();
-    gs.ecs.register::<SimpleMarker<SerializeMe>>();
-    gs.ecs.register::<SerializationHelper>();
-    gs.ecs.register::<DMSerializationHelper>();
-    gs.ecs.register::<Equippable>();
-    gs.ecs.register::<Equipped>();
-    gs.ecs.register::<Weapon>();
-    gs.ecs.register::<Wearable>();
-    gs.ecs.register::<WantsToRemoveItem>();
-    gs.ecs.register::<ParticleLifetime>();
-    gs.ecs.register::<HungerClock>();
-    gs.ecs.register::<ProvidesFood>();
-    gs.ecs.register::<MagicMapper>();
-    gs.ecs.register::<Hidden>();
-    gs.ecs.register::<EntryTrigger>();
-    gs.ecs.register::<EntityMoved>();
-    gs.ecs.register::<SingleActivation>();
-    gs.ecs.register::<BlocksVisibility>();
-    gs.ecs.register::<Door>();
-    gs.ecs.register::<Quips>();
-    gs.ecs.register::<Attributes>();
-    gs.ecs.register::<Skills>();
-    gs.ecs.register::<Pools>();
-    gs.ecs.register::<NaturalAttackDefense>();
-    gs.ecs.register::<LootTable>();
-    gs.ecs.register::<OtherLevelPosition>();
-    gs.ecs.register::<LightSource>();
-    gs.ecs.register::<Initiative>();
-    gs.ecs.register::<MyTurn>();
-    gs.ecs.register::<Faction>();
-    gs.ecs.register::<WantsToApproach>();
-    gs.ecs.register::<WantsToFlee>();
-    gs.ecs.register::<MoveMode>();
-    gs.ecs.register::<Chasing>();
-    gs.ecs.register::<EquipmentChanged>();
-    gs.ecs.register::<Vendor>();
-    gs.ecs.register::<TownPortal>();
-    gs.ecs.register::<TeleportTo>();
-    gs.ecs.register::<ApplyMove>();
-    gs.ecs.register::<ApplyTeleport>();
-    gs.ecs.register::<MagicItem>();
-    gs.ecs.register::<ObfuscatedName>();
-    gs.ecs.register::<IdentifiedItem>();
-    gs.ecs.register::<SpawnParticleBurst>();
-    gs.ecs.register::<SpawnParticleLine>();
-    gs.ecs.register::<CursedItem>();
-    gs.ecs.register::<ProvidesRemoveCurse>();
-    gs.ecs.register::<ProvidesIdentification>();
-    gs.ecs.register::<AttributeBonus>();
-    gs.ecs.register::<Duration>();
-    gs.ecs.register::<StatusEffect>();
-    gs.ecs.register::<KnownSpells>();
-    gs.ecs.register::<SpellTemplate>();
-    gs.ecs.register::<WantsToCastSpell>();
-    gs.ecs.register::<TeachesSpell>();
-    gs.ecs.register::<ProvidesMana>();
-    gs.ecs.register::<Slow>();
-    gs.ecs.register::<DamageOverTime>();
-    gs.ecs.register::<SpecialAbilities>();
-    gs.ecs.register::<TileSize>();
-    gs.ecs.register::<OnDeath>();
-    gs.ecs.register::<AlwaysTargetsSelf>();
-    gs.ecs.register::<Target>();
-    gs.ecs.register::<WantsToShoot>();
-    gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
-
-    raws::load_raws();
-
-    gs.ecs.insert(map::MasterDungeonMap::new());
-    gs.ecs.insert(Map::new(1, 64, 64, &"New Map"));
-    gs.ecs.insert(Point::new(0, 0));
-    let player_entity = spawner::player(&mut gs.ecs, 0, 0);
-    gs.ecs.insert(player_entity);
-    gs.ecs.insert(RunState::MapGeneration {});
-    gs.ecs
-        .insert(systems::particle_system::ParticleBuilder::new());
-    gs.ecs.insert(rex_assets::RexAssets::new());
-
-    gs.generate_world_map(1, 0);
+    saveload_system::import_legacy_save();
+    let mut gs = State::new();
+    // Only a player at the keyboard gets their own bindings; scripts and tests use the defaults
+    gs.keymap = keybindings::load_keymap();
+    gs.recovery_prompt = saveload_system::needs_recovery();
+    if let Some(recording) = &recording {
+        gs.start_replay(recording);
+    }
 
     rltk::main_loop(context, gs)
 }
@@ -3,7 +3,8 @@ use crate::{Hidden, Map, Position, Renderable, Target, TileSize};
 use rltk::prelude::*;
 use specs::prelude::*;
 
-pub fn get_screen_bounds(ecs: &World, _ctx: &mut Rltk) -> (i32, i32, i32, i32) {
+#[must_use]
+pub fn get_screen_bounds(ecs: &World, _ctx: &Rltk) -> (i32, i32, i32, i32) {
     let player_pos = ecs.fetch::<Point>();
     //let (x_chars, y_chars) = ctx.get_char_size();
     let (x_chars, y_chars) = (48, 44);
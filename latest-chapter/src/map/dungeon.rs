@@ -8,6 +8,7 @@ use std::collections::{HashMap, HashSet};
 
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct MasterDungeonMap {
+    pub seed: u64,
     maps: HashMap<i32, Map>,
     pub identified_items: HashSet<String>,
     pub scroll_mappings: HashMap<String, String>,
@@ -16,28 +17,44 @@ pub struct MasterDungeonMap {
 
 impl MasterDungeonMap {
     #[must_use]
-    pub fn new() -> Self {
+    pub fn new(seed: u64) -> Self {
         let mut dm = Self {
+            seed,
             maps: HashMap::new(),
             identified_items: HashSet::new(),
             scroll_mappings: HashMap::new(),
             potion_mappings: HashMap::new(),
         };
+        dm.map_unidentified_names();
+        dm
+    }
 
+    // Gives every scroll and potion in the raws a masked name, keeping any it already has.
+    pub fn map_unidentified_names(&mut self) {
         for scroll_tag in &crate::raws::get_scroll_tags() {
-            let masked_name = make_scroll_name();
-            dm.scroll_mappings
-                .insert(scroll_tag.to_string(), masked_name);
+            if !self.scroll_mappings.contains_key(scroll_tag) {
+                let masked_name = make_scroll_name();
+                self.scroll_mappings
+                    .insert(scroll_tag.to_string(), masked_name);
+            }
         }
 
-        let mut used_potion_names: HashSet<String> = HashSet::new();
+        let mut used_potion_names: HashSet<String> =
+            self.potion_mappings.values().cloned().collect();
         for potion_tag in &crate::raws::get_potion_tags() {
-            let masked_name = make_potion_name(&mut used_potion_names);
-            dm.potion_mappings
-                .insert(potion_tag.to_string(), masked_name);
+            if !self.potion_mappings.contains_key(potion_tag) {
+                let masked_name = make_potion_name(&mut used_potion_names);
+                self.potion_mappings
+                    .insert(potion_tag.to_string(), masked_name);
+            }
         }
+    }
 
-        dm
+    // Each depth gets its own seed, mixed from the world seed, so building a level never depends
+    // on how many dice were rolled before the player got there.
+    #[must_use]
+    pub const fn level_seed(&self, depth: i32) -> u64 {
+        crate::rng::mix(self.seed, depth as u64)
     }
 
     pub fn store_map(&mut self, map: &Map) {
@@ -53,6 +70,11 @@ impl MasterDungeonMap {
             None
         }
     }
+
+    #[must_use]
+    pub fn deepest_depth(&self) -> i32 {
+        self.maps.keys().copied().max().unwrap_or(1)
+    }
 }
 
 fn make_scroll_name() -> String {
@@ -128,6 +150,11 @@ fn make_potion_name(used_names: &mut HashSet<String>) -> String {
 }
 
 fn transition_to_new_map(ecs: &mut World, new_depth: i32) -> Vec<Map> {
+    let level_seed = ecs.fetch::<MasterDungeonMap>().level_seed(new_depth);
+    crate::rng::with_seed(level_seed, || build_new_map(ecs, new_depth))
+}
+
+fn build_new_map(ecs: &mut World, new_depth: i32) -> Vec<Map> {
     let mut builder = level_builder(new_depth, 80, 50);
     builder.build_map();
     if new_depth > 1 {
@@ -288,3 +315,41 @@ pub fn level_transition(ecs: &mut World, new_depth: i32, offset: i32) -> Option<
         Some(transition_to_new_map(ecs, new_depth))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::command::{Cheat, Command};
+    use crate::headless::{test_lock, Simulation};
+    use crate::Map;
+
+    fn go_down(sim: &mut Simulation) -> Vec<super::TileType> {
+        sim.step(Command::Cheat {
+            cheat: Cheat::TeleportToExit,
+        });
+        sim.step(Command::Descend);
+        sim.state.ecs.fetch::<Map>().tiles.clone()
+    }
+
+    #[test]
+    fn levels_are_the_same_whatever_route_reaches_them() {
+        let _lock = test_lock();
+        let mut direct = Simulation::new(31);
+        let second = go_down(&mut direct);
+        let third = go_down(&mut direct);
+        assert_eq!(direct.depth(), 3);
+
+        // Rolling dice and going back up first changes nothing about the deeper levels
+        let mut wandering = Simulation::new(31);
+        for _ in 0..25 {
+            wandering.step(Command::Wait);
+        }
+        go_down(&mut wandering);
+        wandering.step(Command::Ascend);
+        assert_eq!(wandering.depth(), 1);
+        for _ in 0..25 {
+            wandering.step(Command::Wait);
+        }
+        assert!(go_down(&mut wandering) == second);
+        assert!(go_down(&mut wandering) == third);
+    }
+}
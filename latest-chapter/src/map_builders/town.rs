@@ -1,5 +1,5 @@
 use super::{BuilderChain, BuilderMap, InitialMapBuilder, Position, TileType};
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
 pub fn town_builder(new_depth: i32, width: i32, height: i32) -> BuilderChain {
     let mut chain = BuilderChain::new(new_depth, width, height, &"The Town of Bracketon");
@@ -95,7 +95,7 @@ fn spawn_dockers(build_data: &mut BuilderMap) {
     }
 }
 
-fn spawn_townsfolk(build_data: &mut BuilderMap, available_building_tiles: &HashSet<usize>) {
+fn spawn_townsfolk(build_data: &mut BuilderMap, available_building_tiles: &BTreeSet<usize>) {
     for idx in available_building_tiles {
         if crate::rng::roll_dice(1, 10) == 1 {
             let roll = crate::rng::roll_dice(1, 4);
@@ -148,8 +148,8 @@ fn water_and_piers(build_data: &mut BuilderMap) {
     build_data.take_snapshot();
 }
 
-fn town_walls(build_data: &mut BuilderMap) -> (HashSet<usize>, i32) {
-    let mut available_building_tiles: HashSet<usize> = HashSet::new();
+fn town_walls(build_data: &mut BuilderMap) -> (BTreeSet<usize>, i32) {
+    let mut available_building_tiles: BTreeSet<usize> = BTreeSet::new();
     let wall_gap_y = crate::rng::roll_dice(1, build_data.height - 9) + 5;
     for y in 1..build_data.height - 2 {
         if y > wall_gap_y - 4 && y < wall_gap_y + 4 {
@@ -187,7 +187,7 @@ fn town_walls(build_data: &mut BuilderMap) -> (HashSet<usize>, i32) {
 
 fn buildings(
     build_data: &mut BuilderMap,
-    available_building_tiles: &mut HashSet<usize>,
+    available_building_tiles: &mut BTreeSet<usize>,
 ) -> Vec<(i32, i32, i32, i32)> {
     let mut buildings: Vec<(i32, i32, i32, i32)> = Vec::new();
     let mut n_buildings = 0;
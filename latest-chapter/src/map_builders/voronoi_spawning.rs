@@ -1,5 +1,5 @@
 use super::{spawner, BuilderMap, MetaMapBuilder, TileType};
-use std::collections::{hash_map::Entry, HashMap};
+use std::collections::{btree_map::Entry, BTreeMap};
 
 pub struct VoronoiSpawning {}
 
@@ -16,7 +16,7 @@ impl VoronoiSpawning {
 }
 
 fn build(build_data: &mut BuilderMap) {
-    let mut noise_areas: HashMap<i32, Vec<usize>> = HashMap::new();
+    let mut noise_areas: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
     let mut noise = rltk::FastNoise::seeded(crate::rng::roll_dice(1, 65536) as u64);
     noise.set_noise_type(rltk::NoiseType::Cellular);
     noise.set_frequency(0.08);
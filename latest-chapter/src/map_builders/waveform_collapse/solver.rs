@@ -1,5 +1,5 @@
 use super::{Map, MapChunk};
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
 pub struct Solver {
     constraints: Vec<MapChunk>,
@@ -149,7 +149,7 @@ impl Solver {
             }
         } else {
             // There are neighbors, so we try to be compatible with them
-            let mut options_to_check: HashSet<usize> = HashSet::new();
+            let mut options_to_check: BTreeSet<usize> = BTreeSet::new();
             for o in &options {
                 for i in o {
                     options_to_check.insert(*i);
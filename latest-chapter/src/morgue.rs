@@ -0,0 +1,290 @@
+use super::{
+    gamelog, paths, Attribute, Attributes, Consumable, CursedItem, EquipmentSlot, Equipped,
+    HungerClock, HungerState, InBackpack, KnownSpells, MagicItem, Map, Name, ObfuscatedName, Pools,
+    Skills,
+};
+use crate::map::MasterDungeonMap;
+use specs::prelude::*;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LOG_LINES: usize = 50;
+
+// What dealt the killing blow, noted when it lands since the attacker may be gone by the time
+// the morgue is written.
+#[derive(Default)]
+pub struct Killer {
+    pub name: Option<String>,
+}
+
+pub fn record_killer(ecs: &World, creator: Option<Entity>) {
+    let player_entity = ecs.fetch::<Entity>();
+    let name = creator
+        .and_then(|creator| ecs.read_storage::<Name>().get(creator).cloned())
+        .map(|name| name.name)
+        .or_else(|| {
+            ecs.read_storage::<HungerClock>()
+                .get(*player_entity)
+                .filter(|clock| clock.state == HungerState::Starving)
+                .map(|_| "starvation".to_string())
+        });
+    let mut killer = ecs.fetch_mut::<Killer>();
+    if killer.name.is_none() {
+        killer.name = name;
+    }
+}
+
+#[must_use]
+pub fn morgue_dir() -> PathBuf {
+    paths::data_dir().join("morgue")
+}
+
+const fn slot_name(slot: EquipmentSlot) -> &'static str {
+    match slot {
+        EquipmentSlot::Melee => "Weapon",
+        EquipmentSlot::Shield => "Shield",
+        EquipmentSlot::Head => "Head",
+        EquipmentSlot::Torso => "Torso",
+        EquipmentSlot::Legs => "Legs",
+        EquipmentSlot::Feet => "Feet",
+        EquipmentSlot::Hands => "Hands",
+    }
+}
+
+// Unlike the game's own item names, these give the real name and say what it looked like if
+// the player never found out.
+fn item_name(ecs: &World, item: Entity) -> String {
+    let Some(name) = ecs.read_storage::<Name>().get(item).map(|n| n.name.clone()) else {
+        return "Nameless item".to_string();
+    };
+    let mut text = name.clone();
+    if let Some(consumable) = ecs.read_storage::<Consumable>().get(item) {
+        if consumable.max_charges > 1 {
+            let _ = write!(text, " ({} charges)", consumable.charges);
+        }
+    }
+    if ecs.read_storage::<CursedItem>().contains(item) {
+        text.push_str(" (cursed)");
+    }
+    let identified = ecs
+        .fetch::<MasterDungeonMap>()
+        .identified_items
+        .contains(&name);
+    if ecs.read_storage::<MagicItem>().contains(item) && !identified {
+        match ecs.read_storage::<ObfuscatedName>().get(item) {
+            Some(obfuscated) => {
+                let _ = write!(text, " [unidentified {}]", obfuscated.name);
+            }
+            None => text.push_str(" [unidentified]"),
+        }
+    }
+    text
+}
+
+fn attribute_line(label: &str, attribute: &Attribute) -> String {
+    format!(
+        "  {label:<14}{:>3} ({:+})",
+        attribute.base + attribute.modifiers,
+        attribute.bonus
+    )
+}
+
+fn heading(text: &mut String, title: &str) {
+    let _ = write!(text, "\n{title}\n{}\n", "-".repeat(title.len()));
+}
+
+#[must_use]
+pub fn morgue_text(ecs: &World, turns: u32, play_time_secs: u64) -> String {
+    let player = *ecs.fetch::<Entity>();
+    let pools = ecs.read_storage::<Pools>();
+    let map = ecs.fetch::<Map>();
+    let dungeon = ecs.fetch::<MasterDungeonMap>();
+    let mut text = String::new();
+
+    let _ = writeln!(text, "Rusty Roguelike morgue file");
+    let _ = writeln!(text, "===========================\n");
+    let killer = ecs.fetch::<Killer>().name.clone();
+    let _ = writeln!(
+        text,
+        "Killed by {} on {} (depth {}).",
+        killer.as_deref().unwrap_or("unknown causes"),
+        map.name,
+        map.depth
+    );
+    let _ = writeln!(
+        text,
+        "Deepest level reached: {}",
+        dungeon.deepest_depth().max(map.depth)
+    );
+    let _ = writeln!(text, "Turns: {turns}");
+    let _ = writeln!(
+        text,
+        "Play time: {}:{:02}:{:02}",
+        play_time_secs / 3600,
+        play_time_secs / 60 % 60,
+        play_time_secs % 60
+    );
+    let _ = writeln!(text, "World seed: {}", dungeon.seed);
+    if let Some(pools) = pools.get(player) {
+        let _ = writeln!(
+            text,
+            "Level {}, {} XP, {:.1} gold",
+            pools.level, pools.xp, pools.gold
+        );
+        let _ = writeln!(
+            text,
+            "Health {}/{}, mana {}/{}",
+            pools.hit_points.current.max(0),
+            pools.hit_points.max,
+            pools.mana.current,
+            pools.mana.max
+        );
+    }
+
+    if let Some(attributes) = ecs.read_storage::<Attributes>().get(player) {
+        heading(&mut text, "Attributes");
+        for (label, attribute) in [
+            ("Might", &attributes.might),
+            ("Fitness", &attributes.fitness),
+            ("Quickness", &attributes.quickness),
+            ("Intelligence", &attributes.intelligence),
+        ] {
+            let _ = writeln!(text, "{}", attribute_line(label, attribute));
+        }
+    }
+
+    if let Some(skills) = ecs.read_storage::<Skills>().get(player) {
+        heading(&mut text, "Skills");
+        for (skill, level) in &skills.skills {
+            let _ = writeln!(text, "  {:<14}{level:>3}", format!("{skill:?}"));
+        }
+    }
+
+    let entities = ecs.entities();
+    heading(&mut text, "Equipment");
+    for (item, equipped) in (&entities, &ecs.read_storage::<Equipped>()).join() {
+        if equipped.owner == player {
+            let _ = writeln!(
+                text,
+                "  {:<8}{}",
+                slot_name(equipped.slot),
+                item_name(ecs, item)
+            );
+        }
+    }
+
+    heading(&mut text, "Inventory");
+    let mut carried = 0;
+    for (item, pack) in (&entities, &ecs.read_storage::<InBackpack>()).join() {
+        if pack.owner == player {
+            let _ = writeln!(text, "  {}", item_name(ecs, item));
+            carried += 1;
+        }
+    }
+    if carried == 0 {
+        text.push_str("  Nothing\n");
+    }
+
+    heading(&mut text, "Spells");
+    match ecs.read_storage::<KnownSpells>().get(player) {
+        Some(known) if !known.spells.is_empty() => {
+            for spell in &known.spells {
+                let _ = writeln!(text, "  {} ({} mana)", spell.display_name, spell.mana_cost);
+            }
+        }
+        _ => text.push_str("  None\n"),
+    }
+
+    heading(&mut text, "Events");
+    let mut events: Vec<(String, i32)> = gamelog::clone_events().into_iter().collect();
+    events.sort();
+    for (event, count) in events {
+        let _ = writeln!(text, "  {event:<32}{count:>8}");
+    }
+
+    heading(&mut text, "Last messages");
+    for line in gamelog::log_lines(LOG_LINES) {
+        let _ = writeln!(text, "  {line}");
+    }
+
+    text
+}
+
+/// Writes a morgue file describing the player's final state and returns where it went.
+///
+/// # Errors
+///
+/// Fails if the morgue directory can't be written to.
+pub fn write_morgue(ecs: &World, turns: u32, play_time_secs: u64) -> io::Result<PathBuf> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (year, month, day) = crate::gui::civil_from_days((secs / 86400) as i64);
+    let time = secs % 86400;
+    let stamp = format!(
+        "morgue-{year}{month:02}{day:02}-{:02}{:02}{:02}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    );
+    fs::create_dir_all(morgue_dir())?;
+    let mut file = morgue_dir().join(format!("{stamp}.txt"));
+    let mut n = 1;
+    while file.exists() {
+        n += 1;
+        file = morgue_dir().join(format!("{stamp}-{n}.txt"));
+    }
+    fs::write(&file, morgue_text(ecs, turns, play_time_secs))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{morgue_text, Killer};
+    use crate::command::Command;
+    use crate::headless::{test_lock, Simulation};
+    use crate::Map;
+
+    #[test]
+    fn the_morgue_describes_the_run() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(42);
+        for _ in 0..5 {
+            sim.step(Command::Wait);
+        }
+        sim.state.ecs.fetch_mut::<Killer>().name = Some("Rat".to_string());
+
+        let text = morgue_text(&sim.state.ecs, sim.turns(), 3725);
+        let town = sim.state.ecs.fetch::<Map>().name.clone();
+        for line in [
+            format!("Killed by Rat on {town} (depth 1)."),
+            "Deepest level reached: 1".to_string(),
+            "Turns: 5".to_string(),
+            "Play time: 1:02:05".to_string(),
+            "World seed: 42".to_string(),
+            "Level 1, 0 XP, ".to_string(),
+        ] {
+            assert!(
+                text.lines().any(|l| l.starts_with(&line)),
+                "no line starting [{line}] in\n{text}"
+            );
+        }
+        for heading in [
+            "Attributes",
+            "Skills",
+            "Equipment",
+            "Inventory",
+            "Spells",
+            "Events",
+            "Last messages",
+        ] {
+            assert!(text.contains(&format!("\n{heading}\n")), "no {heading}");
+        }
+        assert!(text
+            .lines()
+            .any(|line| line.split_whitespace().eq(["Turn", "5"])));
+    }
+}
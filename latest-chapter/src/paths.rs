@@ -0,0 +1,60 @@
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "rusty-roguelike";
+
+// Where saves and other per-player files live: %APPDATA% on Windows, Application Support on
+// macOS and $XDG_DATA_HOME elsewhere. Falls back to the working directory if none is set.
+// Tests get a directory of their own, so they never read or write the player's files.
+#[must_use]
+pub fn data_dir() -> PathBuf {
+    if cfg!(test) {
+        return env::temp_dir().join(format!("{APP_DIR}-test-{}", std::process::id()));
+    }
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    base.map_or_else(|| PathBuf::from("."), |base| base.join(APP_DIR))
+}
+
+#[must_use]
+pub fn saves_dir() -> PathBuf {
+    data_dir().join("saves")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[must_use]
+pub fn backup_file(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// Writes `data` to a temporary file next to `path` and renames it into place, so a crash can
+/// never leave a half-written file. Whatever was at `path` before becomes its backup.
+///
+/// # Errors
+///
+/// Fails if the directory can't be written to.
+pub fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp = with_suffix(path, ".tmp");
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    if path.exists() {
+        fs::rename(path, backup_file(path))?;
+    }
+    fs::rename(&temp, path)
+}
@@ -1,10 +1,13 @@
 use super::{
-    raws::Reaction, Attributes, BlocksTile, BlocksVisibility, Door, EntityMoved, Equipped, Faction,
+    command::{backpack_slot, Command},
+    keybindings::Action,
+    raws::Reaction,
+    travel, Attributes, BlocksTile, BlocksVisibility, Door, EntityMoved, Equipped, Faction,
     HungerClock, HungerState, Item, Map, Name, Player, Pools, Position, Renderable, RunState,
-    State, Target, TileType, Vendor, VendorMode, Viewshed, WantsToCastSpell, WantsToMelee,
-    WantsToPickupItem, WantsToShoot, Weapon,
+    State, Target, TileType, Vendor, VendorMode, Viewshed, WantsToMelee, WantsToPickupItem,
+    WantsToShoot, Weapon,
 };
-use rltk::{Point, Rltk, VirtualKeyCode};
+use rltk::{Point, Rltk};
 use specs::prelude::*;
 use std::cmp::{max, min};
 
@@ -57,7 +60,7 @@ pub fn end_turn_targeting(ecs: &World) {
     }
 }
 
-fn fire_on_target(ecs: &World) -> RunState {
+pub fn fire_on_target(ecs: &World) -> RunState {
     let targets = ecs.write_storage::<Target>();
     let entities = ecs.entities();
     let mut current_target: Option<Entity> = None;
@@ -93,7 +96,7 @@ fn fire_on_target(ecs: &World) -> RunState {
     )
 }
 
-fn cycle_target(ecs: &World) {
+pub fn cycle_target(ecs: &World) {
     let possible_targets = get_player_target_list(ecs);
     let mut targets = ecs.write_storage::<Target>();
     let entities = ecs.entities();
@@ -105,7 +108,7 @@ fn cycle_target(ecs: &World) {
 
     targets.clear();
     if let Some(current_target) = current_target {
-        if !possible_targets.len() > 1 {
+        if !possible_targets.is_empty() {
             let mut index = 0;
             for (i, target) in possible_targets.iter().enumerate() {
                 if target.1 == current_target {
@@ -113,7 +116,7 @@ fn cycle_target(ecs: &World) {
                 }
             }
 
-            if index > possible_targets.len() - 2 {
+            if index + 1 >= possible_targets.len() {
                 targets
                     .insert(possible_targets[0].1, Target {})
                     .expect("Insert fail");
@@ -283,7 +286,7 @@ pub fn try_previous_level(ecs: &World) -> bool {
     }
 }
 
-fn get_item(ecs: &World) {
+pub fn get_item(ecs: &World) {
     let player_pos = ecs.fetch::<Point>();
     let player_entity = ecs.fetch::<Entity>();
     let entities = ecs.entities();
@@ -316,14 +319,16 @@ fn get_item(ecs: &World) {
     }
 }
 
-fn skip_turn(ecs: &World) -> RunState {
+// Whether the player can see anything that would attack them.
+#[must_use]
+pub fn hostile_in_view(ecs: &World) -> bool {
     let player_entity = ecs.fetch::<Entity>();
     let viewshed_components = ecs.read_storage::<Viewshed>();
     let factions = ecs.read_storage::<Faction>();
 
     let worldmap_resource = ecs.fetch::<Map>();
 
-    let mut can_heal = true;
+    let mut hostile = false;
     let viewshed = viewshed_components.get(*player_entity).unwrap();
     for tile in &viewshed.visible_tiles {
         let idx = worldmap_resource.xy_idx(tile.x, tile.y);
@@ -338,12 +343,18 @@ fn skip_turn(ecs: &World) -> RunState {
                         &crate::raws::RAWS.lock().unwrap(),
                     );
                     if reaction == Reaction::Attack {
-                        can_heal = false;
+                        hostile = true;
                     }
                 }
             }
         });
     }
+    hostile
+}
+
+pub fn skip_turn(ecs: &World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut can_heal = !hostile_in_view(ecs);
 
     let hunger_clocks = ecs.read_storage::<HungerClock>();
     let hc = hunger_clocks.get(*player_entity);
@@ -366,193 +377,163 @@ fn skip_turn(ecs: &World) -> RunState {
     RunState::Ticking
 }
 
-fn use_consumable_hotkey(gs: &State, key: i32) -> RunState {
-    use super::{Consumable, InBackpack, WantsToUseItem};
-
-    let consumables = gs.ecs.read_storage::<Consumable>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let entities = gs.ecs.entities();
-    let mut carried_consumables = Vec::new();
-    for (entity, carried_by, _consumable) in (&entities, &backpack, &consumables).join() {
-        if carried_by.owner == *player_entity {
-            carried_consumables.push(entity);
-        }
-    }
-
-    if (key as usize) < carried_consumables.len() {
+fn use_consumable_hotkey(gs: &mut State, key: i32) -> RunState {
+    use super::{Consumable, InBackpack};
+
+    let carried_consumables: Vec<Entity> = {
+        let consumables = gs.ecs.read_storage::<Consumable>();
+        let backpack = gs.ecs.read_storage::<InBackpack>();
+        let player_entity = gs.ecs.fetch::<Entity>();
+        let entities = gs.ecs.entities();
+        (&entities, &backpack, &consumables)
+            .join()
+            .filter(|(_, carried_by, _)| carried_by.owner == *player_entity)
+            .map(|(entity, _, _)| entity)
+            .collect()
+    };
+
+    if let Some(item) = carried_consumables.get(key as usize).copied() {
         use crate::components::Ranged;
-        if let Some(ranged) = gs
-            .ecs
-            .read_storage::<Ranged>()
-            .get(carried_consumables[key as usize])
-        {
+        if let Some(ranged) = gs.ecs.read_storage::<Ranged>().get(item) {
             return RunState::ShowTargeting {
                 range: ranged.range,
-                item: carried_consumables[key as usize],
+                item,
             };
         }
-        let mut intent = gs.ecs.write_storage::<WantsToUseItem>();
-        intent
-            .insert(
-                *player_entity,
-                WantsToUseItem {
-                    item: carried_consumables[key as usize],
-                    target: None,
-                },
-            )
-            .expect("Unable to insert intent");
-        return RunState::Ticking;
+        if let Some(slot) = backpack_slot(&gs.ecs, item) {
+            return gs.perform(Command::UseItem { slot, target: None });
+        }
     }
-    RunState::Ticking
+    // Nothing on that key, so the turn isn't spent (see command::apply)
+    RunState::AwaitingInput
 }
 
-fn use_spell_hotkey(gs: &State, key: i32) -> RunState {
+fn use_spell_hotkey(gs: &mut State, key: i32) -> RunState {
     use super::raws::find_spell_entity;
     use super::KnownSpells;
 
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let known_spells_storage = gs.ecs.read_storage::<KnownSpells>();
-    let known_spells = &known_spells_storage.get(*player_entity).unwrap().spells;
-
-    if (key as usize) < known_spells.len() {
-        let pools = gs.ecs.read_storage::<Pools>();
-        let player_pools = pools.get(*player_entity).unwrap();
-        if player_pools.mana.current >= known_spells[key as usize].mana_cost {
-            if let Some(spell_entity) =
-                find_spell_entity(&gs.ecs, &known_spells[key as usize].display_name)
-            {
-                use crate::components::Ranged;
-                if let Some(ranged) = gs.ecs.read_storage::<Ranged>().get(spell_entity) {
+    let spell = {
+        let player_entity = gs.ecs.fetch::<Entity>();
+        let known_spells_storage = gs.ecs.read_storage::<KnownSpells>();
+        known_spells_storage
+            .get(*player_entity)
+            .unwrap()
+            .spells
+            .get(key as usize)
+            .cloned()
+    };
+
+    if let Some(spell) = spell {
+        if let Some(spell_entity) = find_spell_entity(&gs.ecs, &spell.display_name) {
+            use crate::components::Ranged;
+            if let Some(ranged) = gs.ecs.read_storage::<Ranged>().get(spell_entity) {
+                let player_entity = gs.ecs.fetch::<Entity>();
+                let pools = gs.ecs.read_storage::<Pools>();
+                if pools.get(*player_entity).unwrap().mana.current >= spell.mana_cost {
                     return RunState::ShowTargeting {
                         range: ranged.range,
                         item: spell_entity,
                     };
                 }
-                let mut intent = gs.ecs.write_storage::<WantsToCastSpell>();
-                intent
-                    .insert(
-                        *player_entity,
-                        WantsToCastSpell {
-                            spell: spell_entity,
-                            target: None,
-                        },
-                    )
-                    .expect("Unable to insert intent");
-                return RunState::Ticking;
             }
-        } else {
-            crate::gamelog::Logger::new()
-                .append(&"You don't have enough mana to cast that!")
-                .log();
         }
+        return gs.perform(Command::Cast {
+            slot: key as usize,
+            target: None,
+        });
     }
 
-    RunState::Ticking
+    RunState::AwaitingInput
 }
 
-pub fn player_input(gs: &State, ctx: &Rltk) -> RunState {
-    // Hotkeys
-    if ctx.shift && ctx.key.is_some() {
-        let key: Option<i32> = match ctx.key.unwrap() {
-            VirtualKeyCode::Key1 => Some(1),
-            VirtualKeyCode::Key2 => Some(2),
-            VirtualKeyCode::Key3 => Some(3),
-            VirtualKeyCode::Key4 => Some(4),
-            VirtualKeyCode::Key5 => Some(5),
-            VirtualKeyCode::Key6 => Some(6),
-            VirtualKeyCode::Key7 => Some(7),
-            VirtualKeyCode::Key8 => Some(8),
-            VirtualKeyCode::Key9 => Some(9),
-            _ => None,
-        };
-        if let Some(key) = key {
-            return use_consumable_hotkey(gs, key - 1);
-        }
-    }
-    if ctx.control && ctx.key.is_some() {
-        let key: Option<i32> = match ctx.key.unwrap() {
-            VirtualKeyCode::Key1 => Some(1),
-            VirtualKeyCode::Key2 => Some(2),
-            VirtualKeyCode::Key3 => Some(3),
-            VirtualKeyCode::Key4 => Some(4),
-            VirtualKeyCode::Key5 => Some(5),
-            VirtualKeyCode::Key6 => Some(6),
-            VirtualKeyCode::Key7 => Some(7),
-            VirtualKeyCode::Key8 => Some(8),
-            VirtualKeyCode::Key9 => Some(9),
-            _ => None,
-        };
-        if let Some(key) = key {
-            return use_spell_hotkey(gs, key - 1);
+pub fn player_input(gs: &mut State, ctx: &Rltk) -> RunState {
+    if ctx.left_click {
+        if let Some(path) =
+            travel::mouse_tile(&gs.ecs, ctx).and_then(|tile| travel::plan_path(&gs.ecs, tile))
+        {
+            gs.travel = Some(travel::Travel::new(&gs.ecs, path));
         }
+        return RunState::AwaitingInput;
     }
 
-    // Player movement
-    match ctx.key {
-        None => return RunState::AwaitingInput, // Nothing happened
-        Some(key) => match key {
-            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
-                return try_move_player(-1, 0, &gs.ecs)
-            }
-
-            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
-                return try_move_player(1, 0, &gs.ecs)
-            }
-
-            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
-                return try_move_player(0, -1, &gs.ecs)
-            }
-
-            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
-                return try_move_player(0, 1, &gs.ecs)
-            }
-
-            // Diagonals
-            VirtualKeyCode::Numpad9 | VirtualKeyCode::U => return try_move_player(1, -1, &gs.ecs),
-
-            VirtualKeyCode::Numpad7 | VirtualKeyCode::Y => return try_move_player(-1, -1, &gs.ecs),
-
-            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => return try_move_player(1, 1, &gs.ecs),
-
-            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => return try_move_player(-1, 1, &gs.ecs),
-
-            // Skip Turn
-            VirtualKeyCode::Numpad5 | VirtualKeyCode::Space => return skip_turn(&gs.ecs),
-
-            // Level changes
-            VirtualKeyCode::Period => {
-                if try_next_level(&gs.ecs) {
-                    return RunState::NextLevel;
-                }
-            }
-            VirtualKeyCode::Comma => {
-                if try_previous_level(&gs.ecs) {
-                    return RunState::PreviousLevel;
-                }
+    let Some(key) = ctx.key else {
+        return RunState::AwaitingInput; // Nothing happened
+    };
+    let Some(action) = gs.keymap.action(key, ctx.shift, ctx.control) else {
+        return RunState::AwaitingInput;
+    };
+    match action {
+        // Player movement
+        Action::MoveWest => gs.perform(Command::Move { dx: -1, dy: 0 }),
+        Action::MoveEast => gs.perform(Command::Move { dx: 1, dy: 0 }),
+        Action::MoveNorth => gs.perform(Command::Move { dx: 0, dy: -1 }),
+        Action::MoveSouth => gs.perform(Command::Move { dx: 0, dy: 1 }),
+
+        // Diagonals
+        Action::MoveNorthEast => gs.perform(Command::Move { dx: 1, dy: -1 }),
+        Action::MoveNorthWest => gs.perform(Command::Move { dx: -1, dy: -1 }),
+        Action::MoveSouthEast => gs.perform(Command::Move { dx: 1, dy: 1 }),
+        Action::MoveSouthWest => gs.perform(Command::Move { dx: -1, dy: 1 }),
+
+        // Skip Turn
+        Action::Wait => gs.perform(Command::Wait),
+
+        // Level changes
+        Action::Descend => gs.perform(Command::Descend),
+        Action::Ascend => gs.perform(Command::Ascend),
+
+        // Picking up items
+        Action::PickUp => gs.perform(Command::PickUp),
+        Action::Inventory => RunState::ShowInventory,
+        Action::Drop => RunState::ShowDropItem,
+        Action::RemoveItem => RunState::ShowRemoveItem,
+
+        // Ranged
+        Action::CycleTarget => gs.perform(Command::CycleTarget),
+        Action::Fire => gs.perform(Command::Fire),
+
+        Action::Look => {
+            let player_pos = *gs.ecs.fetch::<Point>();
+            RunState::ShowLook {
+                x: player_pos.x,
+                y: player_pos.y,
+                index: 0,
+                detail: false,
             }
+        }
 
-            // Picking up items
-            VirtualKeyCode::G => get_item(&gs.ecs),
-            VirtualKeyCode::I => return RunState::ShowInventory,
-            VirtualKeyCode::D => return RunState::ShowDropItem,
-            VirtualKeyCode::R => return RunState::ShowRemoveItem,
+        Action::AutoExplore => {
+            gs.travel = Some(travel::Travel::explore(&gs.ecs));
+            RunState::AwaitingInput
+        }
+        Action::TravelDownStairs => {
+            gs.travel = travel::Travel::to_stairs(&gs.ecs, true);
+            RunState::AwaitingInput
+        }
+        Action::TravelUpStairs => {
+            gs.travel = travel::Travel::to_stairs(&gs.ecs, false);
+            RunState::AwaitingInput
+        }
+        Action::TravelLandmark => {
+            gs.travel = travel::Travel::to_landmark(&gs.ecs);
+            RunState::AwaitingInput
+        }
 
-            // Ranged
-            VirtualKeyCode::V => {
-                cycle_target(&gs.ecs);
-                return RunState::AwaitingInput;
-            }
-            VirtualKeyCode::F => return fire_on_target(&gs.ecs),
+        // Hotkeys
+        Action::UseConsumable(n) => use_consumable_hotkey(gs, n - 1),
+        Action::CastSpell(n) => use_spell_hotkey(gs, n - 1),
 
-            // Save and Quit
-            VirtualKeyCode::Escape => return RunState::SaveGame,
+        Action::MessageLog => RunState::ShowMessageLog {
+            scroll: 0,
+            searching: false,
+        },
+        Action::CharacterSheet => RunState::ShowCharacterSheet,
+        Action::Keybindings => RunState::ShowKeybindings,
 
-            // Cheating!
-            VirtualKeyCode::Backslash => return RunState::ShowCheatMenu,
+        // Save and Quit
+        Action::SaveMenu => gs.slot_menu(true),
 
-            _ => return RunState::AwaitingInput,
-        },
+        // Cheating!
+        Action::CheatMenu => RunState::ShowCheatMenu,
     }
-    RunState::Ticking
 }
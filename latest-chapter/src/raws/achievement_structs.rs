@@ -0,0 +1,9 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Achievement {
+    pub name: String,
+    pub event: String,
+    pub threshold: i32,
+    pub description: String,
+}
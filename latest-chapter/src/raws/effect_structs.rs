@@ -0,0 +1,124 @@
+use serde::Deserialize;
+
+// Effects without a parameter are written as a bare string, e.g. `"single_activation"`, and the
+// rest as `{ "damage": 6 }`. The order they're listed in doesn't matter: when triggered they
+// always fire in the order `effects::triggers` checks them, particles first and damage over
+// time last.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    ProvidesHealing(i32),
+    ProvidesMana(i32),
+    TeachSpell(String),
+    Ranged(i32),
+    Damage(i32),
+    AreaOfEffect(i32),
+    Confusion(i32),
+    MagicMapping,
+    TownPortal,
+    Food,
+    SingleActivation,
+    ParticleLine(Particle),
+    Particle(Particle),
+    RemoveCurse,
+    Identify,
+    Slow(f32),
+    DamageOverTime(i32),
+    TargetSelf,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Particle {
+    pub glyph: char,
+    pub color: String,
+    pub lifetime_ms: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raws::{embedded_raws, parse_raws, RawError, RawFile};
+    use serde_json::{json, Value};
+
+    // Loads the built-in raws plus one potion with the given effects.
+    fn potion_errors(effects: &Value) -> Vec<RawError> {
+        let mut files = embedded_raws();
+        let potion = json!({
+            "name": "Test Potion",
+            "renderable": { "glyph": "!", "fg": "#FF00FF", "bg": "#000000", "order": 2 },
+            "consumable": { "effects": effects },
+        });
+        files.push(RawFile {
+            name: "test.json".to_string(),
+            data: json!({ "items": [potion] }).to_string(),
+        });
+        parse_raws(&files).err().unwrap_or_default()
+    }
+
+    fn rejected_with(effects: &Value, message: &str) {
+        let errors = potion_errors(effects);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].file, "test.json");
+        assert_eq!(errors[0].entry, "item \"Test Potion\"");
+        assert!(
+            errors[0].message.contains(message),
+            "\"{}\" doesn't mention \"{message}\"",
+            errors[0].message
+        );
+    }
+
+    #[test]
+    fn valid_effects_are_accepted() {
+        let errors = potion_errors(&json!([
+            "single_activation",
+            { "damage": 6 },
+            { "particle": { "glyph": "*", "color": "#FF0000", "lifetime_ms": 200.0 } },
+        ]));
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn an_unknown_effect_is_rejected() {
+        rejected_with(&json!([{ "explode": 6 }]), "unknown variant `explode`");
+    }
+
+    #[test]
+    fn a_parameter_of_the_wrong_type_is_rejected() {
+        rejected_with(&json!([{ "damage": "lots" }]), "invalid type");
+    }
+
+    #[test]
+    fn a_missing_parameter_is_rejected() {
+        rejected_with(&json!(["damage"]), "expected newtype variant");
+    }
+
+    #[test]
+    fn a_particle_with_a_missing_or_unknown_field_is_rejected() {
+        rejected_with(
+            &json!([{ "particle": { "glyph": "*", "color": "#FF0000" } }]),
+            "missing field `lifetime_ms`",
+        );
+        rejected_with(
+            &json!([{ "particle": {
+                "glyph": "*", "color": "#FF0000", "lifetime_ms": 200.0, "size": 2
+            } }]),
+            "unknown field `size`",
+        );
+    }
+
+    #[test]
+    fn a_particle_with_a_bad_colour_is_rejected() {
+        rejected_with(
+            &json!([{ "particle": { "glyph": "*", "color": "red", "lifetime_ms": 200.0 } }]),
+            "particle color \"red\" is not a #rrggbb colour",
+        );
+    }
+
+    #[test]
+    fn teaching_an_unknown_spell_is_rejected() {
+        rejected_with(
+            &json!([{ "teach_spell": "Fireball" }]),
+            "teach_spell names unknown spell \"Fireball\"",
+        );
+    }
+}
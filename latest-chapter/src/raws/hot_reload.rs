@@ -0,0 +1,332 @@
+use super::{
+    hash_raw_files, mod_raws, parse_raws, spawn_named_entity, spawn_named_spell, RawError, RawFile,
+    RawMaster, SpawnType, RAWS, RAWS_HASH, RAW_FILES,
+};
+use crate::components::{
+    AlwaysTargetsSelf, AreaOfEffect, AttributeBonus, Attributes, BlocksTile, Confusion, Consumable,
+    DamageOverTime, Duration, EquipmentChanged, Equippable, Equipped, Faction, InBackpack,
+    InflictsDamage, Item, LightSource, LootTable, MagicMapper, MoveMode, Name,
+    NaturalAttackDefense, OnDeath, Pools, ProvidesFood, ProvidesHealing, ProvidesIdentification,
+    ProvidesMana, ProvidesRemoveCurse, Ranged, Renderable, SingleActivation, Skills, Slow,
+    SpawnParticleBurst, SpawnParticleLine, SpecialAbilities, SpellTemplate, StatusEffect,
+    TeachesSpell, TileSize, TownPortal, Vendor, Viewshed, Weapon, Wearable,
+};
+use crate::map::MasterDungeonMap;
+use specs::prelude::*;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+const RAWS_DIR: &str = "raws";
+static RAWS_DIR_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// Reloading reads the raws from here rather than wherever the raws folder was when the game was
+// built.
+pub fn set_raws_dir(dir: PathBuf) {
+    *RAWS_DIR_OVERRIDE.lock().unwrap() = Some(dir);
+}
+
+// The folder set with `set_raws_dir`, or else a raws folder next to the executable or in the
+// working directory, which under `cargo run` is the crate with the raws being edited.
+fn raws_dir() -> Option<PathBuf> {
+    let chosen = RAWS_DIR_OVERRIDE.lock().unwrap().clone();
+    if chosen.is_some() {
+        return chosen;
+    }
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from));
+    exe_dir
+        .into_iter()
+        .chain(std::env::current_dir().ok())
+        .map(|dir| dir.join(RAWS_DIR))
+        .find(|dir| dir.is_dir())
+}
+
+fn source_raws() -> Result<Vec<RawFile>, Vec<RawError>> {
+    let Some(dir) = raws_dir() else {
+        return Err(vec![RawError {
+            file: RAWS_DIR.to_string(),
+            entry: "read".to_string(),
+            message: "no raws folder next to the game or in the working directory; \
+                      use --raws-dir to say where it is"
+                .to_string(),
+        }]);
+    };
+    RAW_FILES
+        .iter()
+        .map(|name| {
+            std::fs::read_to_string(dir.join(name))
+                .map(|data| RawFile {
+                    name: (*name).to_string(),
+                    data,
+                })
+                .map_err(|e| {
+                    vec![RawError {
+                        file: dir.join(name).display().to_string(),
+                        entry: "read".to_string(),
+                        message: e.to_string(),
+                    }]
+                })
+        })
+        .collect()
+}
+
+fn copy_component<T: Component + Clone>(
+    ecs: &World,
+    scratch: &World,
+    template: Entity,
+    entity: Entity,
+) {
+    let source = scratch.read_storage::<T>();
+    let mut target = ecs.write_storage::<T>();
+    if let Some(component) = source.get(template) {
+        target
+            .insert(entity, component.clone())
+            .expect("Unable to insert");
+    } else {
+        target.remove(entity);
+    }
+}
+
+macro_rules! copy_components {
+    ( $ecs:expr, $scratch:expr, $template:expr, $entity:expr, $( $component:ty ),* ) => {
+        $( copy_component::<$component>($ecs, $scratch, $template, $entity); )*
+    };
+}
+
+// Things that live on a spawned mob but are really just the template's numbers. Position,
+// health lost, inventory and AI state are left alone.
+fn reapply_mob(ecs: &World, scratch: &World, template: Entity, entity: Entity) {
+    copy_components!(
+        ecs,
+        scratch,
+        template,
+        entity,
+        Renderable,
+        TileSize,
+        BlocksTile,
+        Attributes,
+        Skills,
+        NaturalAttackDefense,
+        Faction,
+        LightSource,
+        SpecialAbilities,
+        OnDeath,
+        MoveMode,
+        Vendor,
+        LootTable
+    );
+
+    if let (Some(new), Some(pools)) = (
+        scratch.read_storage::<Pools>().get(template),
+        ecs.write_storage::<Pools>().get_mut(entity),
+    ) {
+        let hp_lost = pools.hit_points.max - pools.hit_points.current;
+        let mana_used = pools.mana.max - pools.mana.current;
+        pools.level = new.level;
+        pools.hit_points.max = new.hit_points.max;
+        pools.hit_points.current = i32::max(1, new.hit_points.max - hp_lost);
+        pools.mana.max = new.mana.max;
+        pools.mana.current = i32::max(0, new.mana.max - mana_used);
+    }
+    if let (Some(new), Some(viewshed)) = (
+        scratch.read_storage::<Viewshed>().get(template),
+        ecs.write_storage::<Viewshed>().get_mut(entity),
+    ) {
+        viewshed.range = new.range;
+        viewshed.dirty = true;
+    }
+    ecs.write_storage::<EquipmentChanged>()
+        .insert(entity, EquipmentChanged {})
+        .expect("Unable to insert");
+}
+
+// Items, props and spells: everything the raws describe, including their effects.
+fn reapply_thing(ecs: &World, scratch: &World, template: Entity, entity: Entity) {
+    copy_components!(
+        ecs,
+        scratch,
+        template,
+        entity,
+        Renderable,
+        TileSize,
+        Item,
+        Weapon,
+        Wearable,
+        Equippable,
+        AttributeBonus,
+        LightSource,
+        SpellTemplate,
+        ProvidesHealing,
+        ProvidesMana,
+        TeachesSpell,
+        Ranged,
+        InflictsDamage,
+        AreaOfEffect,
+        Confusion,
+        Duration,
+        MagicMapper,
+        TownPortal,
+        ProvidesFood,
+        SingleActivation,
+        SpawnParticleLine,
+        SpawnParticleBurst,
+        ProvidesRemoveCurse,
+        ProvidesIdentification,
+        Slow,
+        DamageOverTime,
+        AlwaysTargetsSelf
+    );
+
+    if let (Some(new), Some(consumable)) = (
+        scratch.read_storage::<Consumable>().get(template),
+        ecs.write_storage::<Consumable>().get_mut(entity),
+    ) {
+        consumable.max_charges = new.max_charges;
+        consumable.charges = i32::min(consumable.charges, new.max_charges);
+    }
+
+    // Weight and bonuses may have changed for whoever is carrying it.
+    let owner = ecs
+        .read_storage::<Equipped>()
+        .get(entity)
+        .map(|equipped| equipped.owner)
+        .or_else(|| {
+            ecs.read_storage::<InBackpack>()
+                .get(entity)
+                .map(|pack| pack.owner)
+        });
+    if let Some(owner) = owner {
+        ecs.write_storage::<EquipmentChanged>()
+            .insert(owner, EquipmentChanged {})
+            .expect("Unable to insert");
+    }
+}
+
+// Spawns a fresh copy of each named entity into a throwaway world and copies its stats across.
+fn reapply_templates(ecs: &World) -> usize {
+    let targets: Vec<(Entity, String, bool)> = {
+        let entities = ecs.entities();
+        let names = ecs.read_storage::<Name>();
+        let status_effects = ecs.read_storage::<StatusEffect>();
+        let spells = ecs.read_storage::<SpellTemplate>();
+        (&entities, &names, !&status_effects)
+            .join()
+            .map(|(entity, name, ())| (entity, name.name.clone(), spells.contains(entity)))
+            .collect()
+    };
+
+    let mut scratch = crate::new_world();
+    let dungeon_map = MasterDungeonMap::clone(&ecs.fetch::<MasterDungeonMap>());
+    scratch.insert(dungeon_map);
+
+    let raws = RAWS.lock().unwrap();
+    let mut updated = 0;
+    for (entity, name, is_spell) in targets {
+        let template = if is_spell {
+            spawn_named_spell(&raws, &mut scratch, &name)
+        } else {
+            spawn_named_entity(
+                &raws,
+                &mut scratch,
+                &name,
+                SpawnType::AtPosition { x: 0, y: 0 },
+            )
+        };
+        let Some(template) = template else {
+            continue;
+        };
+        if scratch.read_storage::<Pools>().contains(template) {
+            reapply_mob(ecs, &scratch, template, entity);
+        } else {
+            reapply_thing(ecs, &scratch, template, entity);
+        }
+        updated += 1;
+    }
+    updated
+}
+
+/// Re-reads the raws from the raws folder (plus any mods), replaces `RAWS` and updates
+/// everything already spawned to match. Returns how many entities were updated.
+///
+/// # Errors
+///
+/// If the new raws don't load, nothing is changed and every problem is returned.
+pub fn reload_raws(ecs: &mut World) -> Result<usize, Vec<RawError>> {
+    let mut files = source_raws()?;
+    files.extend(mod_raws());
+    let raws = parse_raws(&files)?;
+
+    let mut master = RawMaster::empty();
+    master.load(raws);
+    *RAWS.lock().unwrap() = master;
+    RAWS_HASH.store(hash_raw_files(&files), Ordering::Relaxed);
+
+    // Anything that rolls dice here uses its own generator, so the game's rolls are unchanged.
+    let seed = ecs.fetch::<MasterDungeonMap>().seed;
+    Ok(crate::rng::with_seed(seed, || {
+        ecs.write_resource::<MasterDungeonMap>()
+            .map_unidentified_names();
+        reapply_templates(ecs)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reload_raws, set_raws_dir};
+    use crate::headless::{test_lock, Simulation};
+    use crate::map::MasterDungeonMap;
+    use crate::raws::{embedded_raws, load_raws, spawn_named_mob, SpawnType, RAWS};
+    use crate::{Name, Viewshed};
+    use serde_json::Value;
+    use specs::prelude::*;
+
+    fn barkeep_vision(ecs: &World) -> i32 {
+        let names = ecs.read_storage::<Name>();
+        let viewsheds = ecs.read_storage::<Viewshed>();
+        (&names, &viewsheds)
+            .join()
+            .find(|(name, _)| name.name == "Barkeep")
+            .map(|(_, viewshed)| viewshed.range)
+            .expect("There is no barkeep in town")
+    }
+
+    #[test]
+    fn reloading_changes_the_templates_and_what_was_spawned_from_them() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(5);
+        assert_eq!(barkeep_vision(&sim.state.ecs), 4);
+
+        let dir = crate::paths::data_dir().join("raws");
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in embedded_raws() {
+            let mut data: Value = serde_json::from_str(&file.data).unwrap();
+            if let Some(mobs) = data.get_mut("mobs").and_then(Value::as_array_mut) {
+                for mob in mobs.iter_mut().filter(|mob| mob["name"] == "Barkeep") {
+                    mob["vision_range"] = 9.into();
+                }
+            }
+            std::fs::write(dir.join(&file.name), data.to_string()).unwrap();
+        }
+        set_raws_dir(dir);
+
+        let updated = reload_raws(&mut sim.state.ecs).expect("The raws didn't reload");
+        assert!(updated > 0);
+        assert_eq!(barkeep_vision(&sim.state.ecs), 9);
+
+        let mut fresh = crate::new_world();
+        fresh.insert(MasterDungeonMap::clone(
+            &sim.state.ecs.fetch::<MasterDungeonMap>(),
+        ));
+        spawn_named_mob(
+            &RAWS.lock().unwrap(),
+            &mut fresh,
+            "Barkeep",
+            SpawnType::AtPosition { x: 0, y: 0 },
+        );
+        assert_eq!(barkeep_vision(&fresh), 9);
+
+        load_raws();
+    }
+}
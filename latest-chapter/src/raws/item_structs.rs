@@ -1,9 +1,10 @@
+use super::Effect;
 use serde::Deserialize;
-use std::collections::HashMap;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Item {
     pub name: String,
+    pub description: Option<String>,
     pub renderable: Option<Renderable>,
     pub consumable: Option<Consumable>,
     pub weapon: Option<Weapon>,
@@ -29,7 +30,7 @@ pub struct Renderable {
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Consumable {
-    pub effects: HashMap<String, String>,
+    pub effects: Vec<Effect>,
     pub charges: Option<i32>,
 }
 
@@ -41,7 +42,7 @@ pub struct Weapon {
     pub hit_bonus: i32,
     pub proc_chance: Option<f32>,
     pub proc_target: Option<String>,
-    pub proc_effects: Option<HashMap<String, String>>,
+    pub proc_effects: Option<Vec<Effect>>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -0,0 +1,267 @@
+use super::validation::{entry_label, spawn_row_name, validate, RawError};
+use super::Raws;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub struct RawFile {
+    pub name: String,
+    pub data: String,
+}
+
+// Each top-level array in a raw file, and what its entries are called in error messages.
+const SECTIONS: [(&str, &str); 9] = [
+    ("items", "item"),
+    ("mobs", "mob"),
+    ("props", "prop"),
+    ("spawn_table", "spawn"),
+    ("loot_tables", "loot table"),
+    ("faction_table", "faction"),
+    ("spells", "spell"),
+    ("weapon_traits", "weapon trait"),
+    ("achievements", "achievement"),
+];
+
+// The same thing can spawn at different weights over different depths, so a spawn row is known
+// by its name and depth range together.
+fn entry_name(section: &str, entry: &Value) -> Option<String> {
+    let name = entry.get("name")?.as_str()?;
+    if section != "spawn_table" {
+        return Some(name.to_string());
+    }
+    let depth = |field| {
+        entry
+            .get(field)
+            .map_or_else(|| "?".to_string(), Value::to_string)
+    };
+    Some(spawn_row_name(name, depth("min_depth"), depth("max_depth")))
+}
+
+#[derive(Default)]
+struct Merged {
+    sections: HashMap<&'static str, Vec<(String, Value)>>,
+    // Which file each entry's label was last defined in.
+    sources: HashMap<String, String>,
+    errors: Vec<RawError>,
+}
+
+impl Merged {
+    fn error(&mut self, file: &str, entry: String, message: String) {
+        self.errors.push(RawError {
+            file: file.to_string(),
+            entry,
+            message,
+        });
+    }
+
+    fn add_file(&mut self, file: &RawFile) {
+        let root: Value = match serde_json::from_str(&file.data) {
+            Ok(root) => root,
+            Err(e) => {
+                self.error(&file.name, "invalid JSON".to_string(), e.to_string());
+                return;
+            }
+        };
+        let Value::Object(root) = root else {
+            self.error(
+                &file.name,
+                "top level".to_string(),
+                "expected an object of sections".to_string(),
+            );
+            return;
+        };
+
+        for (key, entries) in root {
+            let Some(&(section, kind)) = SECTIONS.iter().find(|(section, _)| *section == key)
+            else {
+                self.error(&file.name, key, "is not a known section".to_string());
+                continue;
+            };
+            let Value::Array(entries) = entries else {
+                self.error(&file.name, key, "expected an array".to_string());
+                continue;
+            };
+            for (i, entry) in entries.into_iter().enumerate() {
+                self.add_entry(&file.name, section, kind, i, entry);
+            }
+        }
+    }
+
+    // Later files replace entries with the same name, and `"remove": true` deletes one.
+    fn add_entry(&mut self, file: &str, section: &'static str, kind: &str, i: usize, entry: Value) {
+        let Some(name) = entry_name(section, &entry) else {
+            self.error(file, format!("{section}[{i}]"), "has no name".to_string());
+            return;
+        };
+        let label = entry_label(kind, &name);
+        let entries = self.sections.entry(section).or_default();
+        let existing = entries.iter().position(|(n, _)| *n == name);
+
+        if entry.get("remove").and_then(Value::as_bool) == Some(true) {
+            if let Some(existing) = existing {
+                entries.remove(existing);
+                self.sources.remove(&label);
+            } else {
+                self.error(
+                    file,
+                    label,
+                    "removes an entry that doesn't exist".to_string(),
+                );
+            }
+            return;
+        }
+
+        match existing {
+            Some(existing) => {
+                entries[existing].1 = entry;
+                if self
+                    .sources
+                    .get(&label)
+                    .is_some_and(|source| source == file)
+                {
+                    self.error(file, label.clone(), "duplicate name".to_string());
+                }
+            }
+            None => entries.push((name, entry)),
+        }
+        self.sources.insert(label, file.to_string());
+    }
+
+    fn take<T: DeserializeOwned>(&mut self, section: &str, kind: &str) -> Vec<T> {
+        let entries = self.sections.remove(section).unwrap_or_default();
+        let mut result = Vec::new();
+        for (name, entry) in entries {
+            match serde_json::from_value(entry) {
+                Ok(parsed) => result.push(parsed),
+                Err(e) => {
+                    let label = entry_label(kind, &name);
+                    let file = self.sources[&label].clone();
+                    self.error(&file, label, e.to_string());
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Merges raw files in order and validates the result.
+///
+/// # Errors
+///
+/// Returns every problem found, tagged with the file that defined the offending entry.
+pub fn parse_raws(files: &[RawFile]) -> Result<Raws, Vec<RawError>> {
+    let mut merged = Merged::default();
+    for file in files {
+        merged.add_file(file);
+    }
+
+    let raws = Raws {
+        items: merged.take("items", "item"),
+        mobs: merged.take("mobs", "mob"),
+        props: merged.take("props", "prop"),
+        spawn_table: merged.take("spawn_table", "spawn"),
+        loot_tables: merged.take("loot_tables", "loot table"),
+        faction_table: merged.take("faction_table", "faction"),
+        spells: merged.take("spells", "spell"),
+        weapon_traits: merged.take("weapon_traits", "weapon trait"),
+        achievements: merged.take("achievements", "achievement"),
+    };
+
+    let mut errors = merged.errors;
+    if errors.is_empty() {
+        errors = validate(&raws, &merged.sources);
+    }
+    if errors.is_empty() {
+        Ok(raws)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_raws, RawFile};
+    use crate::raws::embedded_raws;
+    use serde_json::{json, Value};
+
+    fn with_mod(data: &Value) -> Vec<RawFile> {
+        let mut files = embedded_raws();
+        files.push(RawFile {
+            name: "mods/test/items.json".to_string(),
+            data: data.to_string(),
+        });
+        files
+    }
+
+    fn built_in_item(name: &str) -> Value {
+        let files = embedded_raws();
+        let file = files.iter().find(|file| file.name == "items.json").unwrap();
+        let items: Value = serde_json::from_str(&file.data).unwrap();
+        items["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["name"] == name)
+            .unwrap()
+            .clone()
+    }
+
+    fn spawn_rows(files: &[RawFile], name: &str) -> Vec<(i32, i32)> {
+        let raws = parse_raws(files).unwrap();
+        raws.spawn_table
+            .iter()
+            .filter(|spawn| spawn.name == name)
+            .map(|spawn| (spawn.weight, spawn.min_depth))
+            .collect()
+    }
+
+    #[test]
+    fn spawn_rows_with_the_same_name_are_all_kept() {
+        assert_eq!(
+            spawn_rows(&embedded_raws(), "Leather Boots"),
+            vec![(1, 2), (5, 1)]
+        );
+    }
+
+    #[test]
+    fn a_later_file_replaces_an_entry() {
+        let mut potion = built_in_item("Health Potion");
+        potion["weight_lbs"] = json!(9.0);
+        let raws = parse_raws(&with_mod(&json!({ "items": [potion] }))).unwrap();
+        let potion = raws
+            .items
+            .iter()
+            .find(|item| item.name == "Health Potion")
+            .unwrap();
+        assert_eq!(potion.weight_lbs, Some(9.0));
+        assert_eq!(
+            raws.items.len(),
+            parse_raws(&embedded_raws()).unwrap().items.len()
+        );
+    }
+
+    #[test]
+    fn remove_deletes_an_entry() {
+        let files = with_mod(&json!({ "spawn_table": [
+            { "name": "Leather Boots", "min_depth": 2, "max_depth": 100, "remove": true }
+        ] }));
+        assert_eq!(spawn_rows(&files, "Leather Boots"), vec![(5, 1)]);
+
+        let errors = parse_raws(&with_mod(&json!({ "items": [
+            { "name": "Nothing At All", "remove": true }
+        ] })))
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "removes an entry that doesn't exist");
+    }
+
+    #[test]
+    fn a_name_used_twice_in_one_file_is_an_error() {
+        let potion = built_in_item("Health Potion");
+        let errors = parse_raws(&with_mod(&json!({ "items": [potion, potion] }))).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].file, "mods/test/items.json");
+        assert_eq!(errors[0].entry, "item \"Health Potion\"");
+        assert_eq!(errors[0].message, "duplicate name");
+    }
+}
@@ -5,6 +5,7 @@ use std::collections::HashMap;
 #[derive(Deserialize, Debug)]
 pub struct Mob {
     pub name: String,
+    pub description: Option<String>,
     pub renderable: Option<Renderable>,
     pub blocks_tile: bool,
     pub vision_range: i32,
@@ -1,3 +1,5 @@
+mod effect_structs;
+pub use effect_structs::{Effect, Particle};
 mod item_structs;
 use item_structs::{Item, MagicItem, Renderable};
 mod mob_structs;
@@ -14,15 +16,43 @@ mod spell_structs;
 pub use spell_structs::Spell;
 mod weapon_traits;
 pub use weapon_traits::*;
+mod achievement_structs;
+pub use achievement_structs::Achievement;
 
 mod rawmaster;
 pub use rawmaster::*;
+mod validation;
+pub use validation::RawError;
+mod merge;
+pub use merge::{parse_raws, RawFile};
+mod hot_reload;
+pub use hot_reload::{reload_raws, set_raws_dir};
 use serde::Deserialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{LazyLock, Mutex};
 
-rltk::embedded_resource!(RAW_FILE, "../../raws/spawns.json");
+rltk::embedded_resource!(SPAWNS_FILE, "../../raws/spawns.json");
+rltk::embedded_resource!(ITEMS_FILE, "../../raws/items.json");
+rltk::embedded_resource!(MOBS_FILE, "../../raws/mobs.json");
+rltk::embedded_resource!(PROPS_FILE, "../../raws/props.json");
+rltk::embedded_resource!(SPELLS_FILE, "../../raws/spells.json");
+rltk::embedded_resource!(FACTIONS_FILE, "../../raws/factions.json");
+rltk::embedded_resource!(ACHIEVEMENTS_FILE, "../../raws/achievements.json");
+
+const RAW_FILES: [&str; 7] = [
+    "spawns.json",
+    "items.json",
+    "mobs.json",
+    "props.json",
+    "spells.json",
+    "factions.json",
+    "achievements.json",
+];
+const MODS_DIR: &str = "mods";
 
 pub static RAWS: LazyLock<Mutex<RawMaster>> = LazyLock::new(|| Mutex::new(RawMaster::empty()));
+static RAWS_HASH: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Deserialize, Debug)]
 pub struct Raws {
@@ -34,19 +64,121 @@ pub struct Raws {
     pub faction_table: Vec<FactionInfo>,
     pub spells: Vec<Spell>,
     pub weapon_traits: Vec<WeaponTrait>,
+    pub achievements: Vec<Achievement>,
 }
 
-pub fn load_raws() {
-    rltk::link_resource!(RAW_FILE, "../../raws/spawns.json");
+#[must_use]
+pub fn embedded_raws() -> Vec<RawFile> {
+    rltk::link_resource!(SPAWNS_FILE, "../../raws/spawns.json");
+    rltk::link_resource!(ITEMS_FILE, "../../raws/items.json");
+    rltk::link_resource!(MOBS_FILE, "../../raws/mobs.json");
+    rltk::link_resource!(PROPS_FILE, "../../raws/props.json");
+    rltk::link_resource!(SPELLS_FILE, "../../raws/spells.json");
+    rltk::link_resource!(FACTIONS_FILE, "../../raws/factions.json");
+    rltk::link_resource!(ACHIEVEMENTS_FILE, "../../raws/achievements.json");
+
+    RAW_FILES
+        .iter()
+        .map(|name| {
+            // Retrieve the raw data as an array of u8 (8-bit unsigned chars)
+            let raw_data = rltk::embedding::EMBED
+                .lock()
+                .get_resource(format!("../../raws/{name}"))
+                .unwrap();
+            RawFile {
+                name: (*name).to_string(),
+                data: std::str::from_utf8(raw_data)
+                    .expect("Unable to convert to a valid UTF-8 string.")
+                    .to_string(),
+            }
+        })
+        .collect()
+}
 
-    // Retrieve the raw data as an array of u8 (8-bit unsigned chars)
-    let raw_data = rltk::embedding::EMBED
-        .lock()
-        .get_resource("../../raws/spawns.json".to_string())
-        .unwrap();
-    let raw_string =
-        std::str::from_utf8(raw_data).expect("Unable to convert to a valid UTF-8 string.");
-    let decoder: Raws = serde_json::from_str(raw_string).expect("Unable to parse JSON");
+/// Reads a file, or every .json file in a directory (and its subdirectories) in name order.
+///
+/// # Errors
+///
+/// Fails if anything under `path` can't be read.
+pub fn read_raw_files(path: &Path, files: &mut Vec<RawFile>) -> std::io::Result<()> {
+    if path.is_dir() {
+        let mut entries = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "json") {
+                read_raw_files(&entry, files)?;
+            }
+        }
+    } else {
+        files.push(RawFile {
+            name: path.display().to_string(),
+            data: std::fs::read_to_string(path)?,
+        });
+    }
+    Ok(())
+}
+
+// Mods are folders in the mods directory next to the executable, applied in name order on
+// top of the built-in raws.
+#[must_use]
+pub fn mod_raws() -> Vec<RawFile> {
+    let mut files = Vec::new();
+    if cfg!(target_arch = "wasm32") {
+        return files;
+    }
+    let Some(mods) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(MODS_DIR)))
+    else {
+        return files;
+    };
+    if mods.is_dir() {
+        if let Err(e) = read_raw_files(&mods, &mut files) {
+            rltk::console::log(format!("Unable to read mods from {}: {e}", mods.display()));
+        }
+    }
+    files
+}
+
+// FNV-1a over every loaded file, so saves can tell when the game data has changed under them.
+fn hash_raw_files(files: &[RawFile]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for file in files {
+        for byte in file.name.bytes().chain(file.data.bytes()) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+#[must_use]
+pub fn raws_hash() -> u64 {
+    RAWS_HASH.load(Ordering::Relaxed)
+}
+
+// A broken mod shouldn't stop the game starting, so if the mods don't load the game carries on
+// with the built-in raws alone.
+pub fn load_raws() {
+    let mut files = embedded_raws();
+    files.extend(mod_raws());
+    let decoder = match parse_raws(&files) {
+        Ok(raws) => raws,
+        Err(errors) => {
+            for error in &errors {
+                rltk::console::log(error.to_string());
+            }
+            rltk::console::log(format!(
+                "{} problem(s) found in the raw files; starting without mods",
+                errors.len()
+            ));
+            files = embedded_raws();
+            parse_raws(&files).expect("The built-in raws are invalid")
+        }
+    };
 
     RAWS.lock().unwrap().load(decoder);
+    RAWS_HASH.store(hash_raw_files(&files), Ordering::Relaxed);
 }
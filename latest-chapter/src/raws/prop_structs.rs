@@ -1,10 +1,10 @@
-use super::Renderable;
+use super::{Effect, Renderable};
 use serde::Deserialize;
-use std::collections::HashMap;
 
 #[derive(Deserialize, Debug)]
 pub struct Prop {
     pub name: String,
+    pub description: Option<String>,
     pub renderable: Option<Renderable>,
     pub hidden: Option<bool>,
     pub blocks_tile: Option<bool>,
@@ -16,5 +16,5 @@ pub struct Prop {
 
 #[derive(Deserialize, Debug)]
 pub struct EntryTrigger {
-    pub effects: HashMap<String, String>,
+    pub effects: Vec<Effect>,
 }
@@ -1,4 +1,4 @@
-use super::{faction_structs::Reaction, Raws};
+use super::{faction_structs::Reaction, Effect, Raws};
 use crate::components::{
     AlwaysTargetsSelf, AreaOfEffect, Attribute, AttributeBonus, Attributes, BlocksTile,
     BlocksVisibility, Confusion, CursedItem, DamageOverTime, Door, Duration, EntryTrigger,
@@ -15,7 +15,7 @@ use crate::{attr_bonus, mana_at_level, npc_hp};
 use regex::Regex;
 use specs::prelude::*;
 use specs::saveload::{MarkedBuilder, SimpleMarker};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::LazyLock;
 
 #[must_use]
@@ -75,6 +75,7 @@ impl RawMaster {
                 faction_table: Vec::new(),
                 spells: Vec::new(),
                 weapon_traits: Vec::new(),
+                achievements: Vec::new(),
             },
             item_index: HashMap::new(),
             mob_index: HashMap::new(),
@@ -322,6 +323,26 @@ fn find_slot_for_equippable_item(tag: &str, raws: &RawMaster) -> EquipmentSlot {
     panic!("Trying to equip {tag}, but it has no slot tag.");
 }
 
+// The description written in the raws for an item, mob or prop, if it has one.
+#[must_use]
+pub fn get_description(name: &str, raws: &RawMaster) -> Option<String> {
+    let item = raws
+        .item_index
+        .get(name)
+        .map(|idx| &raws.raws.items[*idx].description);
+    let mob = || {
+        raws.mob_index
+            .get(name)
+            .map(|idx| &raws.raws.mobs[*idx].description)
+    };
+    let prop = || {
+        raws.prop_index
+            .get(name)
+            .map(|idx| &raws.raws.props[*idx].description)
+    };
+    item.or_else(mob).or_else(prop).cloned().flatten()
+}
+
 #[must_use]
 pub fn get_vendor_items(categories: &[String], raws: &RawMaster) -> Vec<(String, f32)> {
     let mut result: Vec<(String, f32)> = Vec::new();
@@ -367,6 +388,11 @@ pub fn get_potion_tags() -> Vec<String> {
     result
 }
 
+#[must_use]
+pub fn get_achievements() -> Vec<super::Achievement> {
+    super::RAWS.lock().unwrap().raws.achievements.clone()
+}
+
 #[must_use]
 pub fn is_tag_magic(tag: &str) -> bool {
     let raws = super::RAWS.lock().unwrap();
@@ -427,91 +453,51 @@ pub fn string_to_slot(slot: &str) -> EquipmentSlot {
     }
 }
 
-fn parse_particle_line(n: &str) -> SpawnParticleLine {
-    let tokens: Vec<_> = n.split(';').collect();
-    SpawnParticleLine {
-        glyph: rltk::to_cp437(tokens[0].chars().next().unwrap()),
-        color: rltk::RGB::from_hex(tokens[1]).expect("Bad RGB"),
-        lifetime_ms: tokens[2].parse::<f32>().unwrap(),
-    }
+// Colours are checked when the raws load, so this fallback is never seen in play.
+fn particle_color(hex: &str) -> rltk::RGB {
+    rltk::RGB::from_hex(hex).unwrap_or_else(|_| rltk::RGB::named(rltk::WHITE))
 }
 
-fn parse_particle(n: &str) -> SpawnParticleBurst {
-    let tokens: Vec<_> = n.split(';').collect();
-    SpawnParticleBurst {
-        glyph: rltk::to_cp437(tokens[0].chars().next().unwrap()),
-        color: rltk::RGB::from_hex(tokens[1]).expect("Bad RGB"),
-        lifetime_ms: tokens[2].parse::<f32>().unwrap(),
+fn apply_effects<'a>(effects: &[Effect], mut eb: EntityBuilder<'a>) -> EntityBuilder<'a> {
+    for effect in effects {
+        eb = match effect {
+            Effect::ProvidesHealing(heal_amount) => eb.with(ProvidesHealing {
+                heal_amount: *heal_amount,
+            }),
+            Effect::ProvidesMana(mana_amount) => eb.with(ProvidesMana {
+                mana_amount: *mana_amount,
+            }),
+            Effect::TeachSpell(spell) => eb.with(TeachesSpell {
+                spell: spell.clone(),
+            }),
+            Effect::Ranged(range) => eb.with(Ranged { range: *range }),
+            Effect::Damage(damage) => eb.with(InflictsDamage { damage: *damage }),
+            Effect::AreaOfEffect(radius) => eb.with(AreaOfEffect { radius: *radius }),
+            Effect::Confusion(turns) => eb.with(Confusion {}).with(Duration { turns: *turns }),
+            Effect::MagicMapping => eb.with(MagicMapper {}),
+            Effect::TownPortal => eb.with(TownPortal {}),
+            Effect::Food => eb.with(ProvidesFood {}),
+            Effect::SingleActivation => eb.with(SingleActivation {}),
+            Effect::ParticleLine(particle) => eb.with(SpawnParticleLine {
+                glyph: rltk::to_cp437(particle.glyph),
+                color: particle_color(&particle.color),
+                lifetime_ms: particle.lifetime_ms,
+            }),
+            Effect::Particle(particle) => eb.with(SpawnParticleBurst {
+                glyph: rltk::to_cp437(particle.glyph),
+                color: particle_color(&particle.color),
+                lifetime_ms: particle.lifetime_ms,
+            }),
+            Effect::RemoveCurse => eb.with(ProvidesRemoveCurse {}),
+            Effect::Identify => eb.with(ProvidesIdentification {}),
+            Effect::Slow(initiative_penalty) => eb.with(Slow {
+                initiative_penalty: *initiative_penalty,
+            }),
+            Effect::DamageOverTime(damage) => eb.with(DamageOverTime { damage: *damage }),
+            Effect::TargetSelf => eb.with(AlwaysTargetsSelf {}),
+        };
     }
-}
-
-macro_rules! apply_effects {
-    ( $effects:expr, $eb:expr ) => {
-        for effect in $effects.iter() {
-            let effect_name = effect.0.as_str();
-            match effect_name {
-                "provides_healing" => {
-                    $eb = $eb.with(ProvidesHealing {
-                        heal_amount: effect.1.parse::<i32>().unwrap(),
-                    })
-                }
-                "provides_mana" => {
-                    $eb = $eb.with(ProvidesMana {
-                        mana_amount: effect.1.parse::<i32>().unwrap(),
-                    })
-                }
-                "teach_spell" => {
-                    $eb = $eb.with(TeachesSpell {
-                        spell: effect.1.to_string(),
-                    })
-                }
-                "ranged" => {
-                    $eb = $eb.with(Ranged {
-                        range: effect.1.parse::<i32>().unwrap(),
-                    })
-                }
-                "damage" => {
-                    $eb = $eb.with(InflictsDamage {
-                        damage: effect.1.parse::<i32>().unwrap(),
-                    })
-                }
-                "area_of_effect" => {
-                    $eb = $eb.with(AreaOfEffect {
-                        radius: effect.1.parse::<i32>().unwrap(),
-                    })
-                }
-                "confusion" => {
-                    $eb = $eb.with(Confusion {});
-                    $eb = $eb.with(Duration {
-                        turns: effect.1.parse::<i32>().unwrap(),
-                    });
-                }
-                "magic_mapping" => $eb = $eb.with(MagicMapper {}),
-                "town_portal" => $eb = $eb.with(TownPortal {}),
-                "food" => $eb = $eb.with(ProvidesFood {}),
-                "single_activation" => $eb = $eb.with(SingleActivation {}),
-                "particle_line" => $eb = $eb.with(parse_particle_line(&effect.1)),
-                "particle" => $eb = $eb.with(parse_particle(&effect.1)),
-                "remove_curse" => $eb = $eb.with(ProvidesRemoveCurse {}),
-                "identify" => $eb = $eb.with(ProvidesIdentification {}),
-                "slow" => {
-                    $eb = $eb.with(Slow {
-                        initiative_penalty: effect.1.parse::<f32>().unwrap(),
-                    })
-                }
-                "damage_over_time" => {
-                    $eb = $eb.with(DamageOverTime {
-                        damage: effect.1.parse::<i32>().unwrap(),
-                    })
-                }
-                "target_self" => $eb = $eb.with(AlwaysTargetsSelf {}),
-                _ => rltk::console::log(format!(
-                    "Warning: consumable effect {} not implemented.",
-                    effect_name
-                )),
-            }
-        }
-    };
+    eb
 }
 
 pub fn spawn_named_item(
@@ -560,7 +546,7 @@ pub fn spawn_named_item(
                 max_charges,
                 charges: max_charges,
             });
-            apply_effects!(consumable.effects, eb);
+            eb = apply_effects(&consumable.effects, eb);
         }
 
         if let Some(weapon) = &item_template.weapon {
@@ -588,7 +574,7 @@ pub fn spawn_named_item(
             }
             eb = eb.with(wpn);
             if let Some(proc_effects) = &weapon.proc_effects {
-                apply_effects!(proc_effects, eb);
+                eb = apply_effects(proc_effects, eb);
             }
         }
 
@@ -798,7 +784,7 @@ pub fn spawn_named_mob(
         eb = eb.with(EquipmentChanged {});
 
         let mut skills = Skills {
-            skills: HashMap::new(),
+            skills: BTreeMap::new(),
         };
         skills.skills.insert(Skill::Melee, 1);
         skills.skills.insert(Skill::Defense, 1);
@@ -972,7 +958,7 @@ pub fn spawn_named_prop(
         }
         if let Some(entry_trigger) = &prop_template.entry_trigger {
             eb = eb.with(EntryTrigger {});
-            apply_effects!(entry_trigger.effects, eb);
+            eb = apply_effects(&entry_trigger.effects, eb);
         }
         if let Some(light) = &prop_template.light {
             eb = eb.with(LightSource {
@@ -1002,7 +988,7 @@ pub fn spawn_named_spell(raws: &RawMaster, ecs: &mut World, key: &str) -> Option
         eb = eb.with(Name {
             name: spell_template.name.clone(),
         });
-        apply_effects!(spell_template.effects, eb);
+        eb = apply_effects(&spell_template.effects, eb);
 
         return Some(eb.build());
     }
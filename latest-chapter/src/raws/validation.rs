@@ -0,0 +1,619 @@
+use super::item_structs::Renderable;
+use super::{Effect, Raws};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::LazyLock;
+
+#[derive(Clone, Debug)]
+pub struct RawError {
+    pub file: String,
+    pub entry: String,
+    pub message: String,
+}
+
+impl fmt::Display for RawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.file, self.entry, self.message)
+    }
+}
+
+#[must_use]
+pub(super) fn entry_label(kind: &str, name: &str) -> String {
+    format!("{kind} \"{name}\"")
+}
+
+#[must_use]
+pub(super) fn spawn_row_name(
+    name: &str,
+    min_depth: impl fmt::Display,
+    max_depth: impl fmt::Display,
+) -> String {
+    format!("{name} at depths {min_depth}-{max_depth}")
+}
+
+struct Validator<'a> {
+    sources: &'a HashMap<String, String>,
+    errors: Vec<RawError>,
+    entities: HashSet<&'a str>,
+    spells: HashSet<&'a str>,
+    loot_tables: HashSet<&'a str>,
+    factions: HashSet<&'a str>,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&mut self, entry: &str, message: String) {
+        self.errors.push(RawError {
+            file: self
+                .sources
+                .get(entry)
+                .map_or("raws", String::as_str)
+                .to_string(),
+            entry: entry.to_string(),
+            message,
+        });
+    }
+
+    // Items, mobs and props share one namespace, so the same name in two sections is ambiguous.
+    fn unique<I>(&mut self, names: I) -> HashSet<&'a str>
+    where
+        I: Iterator<Item = (&'static str, &'a str)>,
+    {
+        let mut seen = HashSet::new();
+        for (kind, name) in names {
+            if !seen.insert(name) {
+                self.error(&entry_label(kind, name), "duplicate name".to_string());
+            }
+        }
+        seen
+    }
+
+    fn renderable(&mut self, entry: &str, renderable: &Renderable) {
+        if renderable.glyph.is_empty() {
+            self.error(entry, "renderable glyph is empty".to_string());
+        }
+        self.color(entry, "renderable fg", &renderable.fg);
+        self.color(entry, "renderable bg", &renderable.bg);
+        for (field, size) in [("x_size", renderable.x_size), ("y_size", renderable.y_size)] {
+            if size.is_some_and(|size| size < 1) {
+                self.error(entry, format!("renderable {field} must be at least 1"));
+            }
+        }
+    }
+
+    fn color(&mut self, entry: &str, field: &str, color: &str) {
+        if rltk::RGB::from_hex(color).is_err() {
+            self.error(
+                entry,
+                format!("{field} \"{color}\" is not a #rrggbb colour"),
+            );
+        }
+    }
+
+    fn dice(&mut self, entry: &str, field: &str, dice: &str) {
+        static DICE_RE: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^\d+d\d+([\+\-]\d+)?$").unwrap());
+
+        if !DICE_RE.is_match(dice) {
+            self.error(
+                entry,
+                format!("{field} \"{dice}\" is not a dice string like 1d6 or 2d4+1"),
+            );
+        }
+    }
+
+    fn effects(&mut self, entry: &str, effects: &[Effect]) {
+        for effect in effects {
+            match effect {
+                Effect::TeachSpell(spell) if !self.spells.contains(spell.as_str()) => {
+                    self.error(
+                        entry,
+                        format!("teach_spell names unknown spell \"{spell}\""),
+                    );
+                }
+                Effect::Particle(particle) | Effect::ParticleLine(particle) => {
+                    self.color(entry, "particle color", &particle.color);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn items(&mut self, raws: &Raws) {
+        for item in &raws.items {
+            let entry = entry_label("item", &item.name);
+            if let Some(renderable) = &item.renderable {
+                self.renderable(&entry, renderable);
+            }
+            if let Some(consumable) = &item.consumable {
+                self.effects(&entry, &consumable.effects);
+                if consumable.charges.is_some_and(|charges| charges < 1) {
+                    self.error(&entry, "consumable charges must be at least 1".to_string());
+                }
+            }
+            if let Some(weapon) = &item.weapon {
+                if weapon.range != "melee" && weapon.range.parse::<i32>().is_err() {
+                    self.error(
+                        &entry,
+                        format!(
+                            "weapon range \"{}\" is not \"melee\" or a number",
+                            weapon.range
+                        ),
+                    );
+                }
+                if !["Might", "Quickness"].contains(&weapon.attribute.as_str()) {
+                    self.error(
+                        &entry,
+                        format!(
+                            "weapon attribute \"{}\" is not Might or Quickness",
+                            weapon.attribute
+                        ),
+                    );
+                }
+                self.dice(&entry, "weapon base_damage", &weapon.base_damage);
+                if weapon
+                    .proc_chance
+                    .is_some_and(|c| !(0.0..=1.0).contains(&c))
+                {
+                    self.error(
+                        &entry,
+                        "weapon proc_chance must be between 0 and 1".to_string(),
+                    );
+                }
+                match weapon.proc_target.as_deref() {
+                    None | Some("Self" | "Target") => {}
+                    Some(target) => self.error(
+                        &entry,
+                        format!("weapon proc_target \"{target}\" is not Self or Target"),
+                    ),
+                }
+                if weapon.proc_chance.is_some() && weapon.proc_target.is_none() {
+                    self.error(
+                        &entry,
+                        "weapon has a proc_chance but no proc_target".to_string(),
+                    );
+                }
+                if let Some(proc_effects) = &weapon.proc_effects {
+                    self.effects(&entry, proc_effects);
+                }
+            }
+            if let Some(wearable) = &item.wearable {
+                if !SLOTS.contains(&wearable.slot.as_str()) {
+                    self.error(
+                        &entry,
+                        format!(
+                            "wearable slot \"{}\" is not one of {}",
+                            wearable.slot,
+                            SLOTS.join(", ")
+                        ),
+                    );
+                }
+            }
+            if let Some(magic) = &item.magic {
+                if !["common", "rare", "legendary"].contains(&magic.class.as_str()) {
+                    self.error(
+                        &entry,
+                        format!(
+                            "magic class \"{}\" is not common, rare or legendary",
+                            magic.class
+                        ),
+                    );
+                }
+                if magic.naming.is_empty() {
+                    self.error(&entry, "magic naming is empty".to_string());
+                }
+            }
+            if let Some(template) = &item.template_magic {
+                if item.weapon.is_none() && item.wearable.is_none() {
+                    self.error(
+                        &entry,
+                        "template_magic needs a weapon or wearable".to_string(),
+                    );
+                }
+                if template.bonus_min > template.bonus_max {
+                    self.error(
+                        &entry,
+                        "template_magic bonus_min is greater than bonus_max".to_string(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn mobs(&mut self, raws: &Raws) {
+        let magic_names = magic_item_names(raws);
+        let equippable: HashSet<&str> = raws
+            .items
+            .iter()
+            .filter(|item| item.weapon.is_some() || item.wearable.is_some())
+            .map(|item| item.name.as_str())
+            .chain(magic_names.iter().map(String::as_str))
+            .collect();
+        let categories: HashSet<&str> = raws
+            .items
+            .iter()
+            .filter_map(|item| item.vendor_category.as_deref())
+            .collect();
+
+        for mob in &raws.mobs {
+            let entry = entry_label("mob", &mob.name);
+            if let Some(renderable) = &mob.renderable {
+                self.renderable(&entry, renderable);
+            }
+            if !["static", "random", "random_waypoint"].contains(&mob.movement.as_str()) {
+                self.error(
+                    &entry,
+                    format!(
+                        "movement \"{}\" is not static, random or random_waypoint",
+                        mob.movement
+                    ),
+                );
+            }
+            if let Some(skills) = &mob.skills {
+                let mut names: Vec<_> = skills.keys().collect();
+                names.sort();
+                for skill in names {
+                    if !["Melee", "Defense", "Magic"].contains(&skill.as_str()) {
+                        self.error(
+                            &entry,
+                            format!("skill \"{skill}\" is not Melee, Defense or Magic"),
+                        );
+                    }
+                }
+            }
+            for item in mob.equipped.iter().flatten() {
+                if !self.entities.contains(item.as_str()) {
+                    self.error(&entry, format!("equips unknown item \"{item}\""));
+                } else if !equippable.contains(item.as_str()) {
+                    self.error(
+                        &entry,
+                        format!("equips \"{item}\", which is not a weapon or wearable"),
+                    );
+                }
+            }
+            if let Some(natural) = &mob.natural {
+                for attack in natural.attacks.iter().flatten() {
+                    self.dice(
+                        &entry,
+                        &format!("natural attack \"{}\" damage", attack.name),
+                        &attack.damage,
+                    );
+                }
+            }
+            if let Some(loot_table) = &mob.loot_table {
+                if !self.loot_tables.contains(loot_table.as_str()) {
+                    self.error(&entry, format!("unknown loot_table \"{loot_table}\""));
+                }
+            }
+            if let Some(light) = &mob.light {
+                self.color(&entry, "light color", &light.color);
+            }
+            if let Some(faction) = &mob.faction {
+                if !self.factions.contains(faction.as_str()) {
+                    self.error(&entry, format!("unknown faction \"{faction}\""));
+                }
+            }
+            if let Some(gold) = &mob.gold {
+                self.dice(&entry, "gold", gold);
+            }
+            for category in mob.vendor.iter().flatten() {
+                if !categories.contains(category.as_str()) {
+                    self.error(
+                        &entry,
+                        format!("sells vendor category \"{category}\", which no item has"),
+                    );
+                }
+            }
+            for (field, abilities) in [("ability", &mob.abilities), ("on_death", &mob.on_death)] {
+                for ability in abilities.iter().flatten() {
+                    if !self.spells.contains(ability.spell.as_str()) {
+                        self.error(
+                            &entry,
+                            format!("{field} uses unknown spell \"{}\"", ability.spell),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn props(&mut self, raws: &Raws) {
+        for prop in &raws.props {
+            let entry = entry_label("prop", &prop.name);
+            if let Some(renderable) = &prop.renderable {
+                self.renderable(&entry, renderable);
+            }
+            if let Some(trigger) = &prop.entry_trigger {
+                self.effects(&entry, &trigger.effects);
+            }
+            if let Some(light) = &prop.light {
+                self.color(&entry, "light color", &light.color);
+            }
+        }
+    }
+
+    fn tables(&mut self, raws: &Raws) {
+        for spawn in &raws.spawn_table {
+            let entry = entry_label(
+                "spawn",
+                &spawn_row_name(&spawn.name, spawn.min_depth, spawn.max_depth),
+            );
+            if !self.entities.contains(spawn.name.as_str()) {
+                self.error(&entry, "not an item, mob or prop".to_string());
+            }
+            if spawn.min_depth > spawn.max_depth {
+                self.error(&entry, "min_depth is greater than max_depth".to_string());
+            }
+        }
+
+        for table in &raws.loot_tables {
+            let entry = entry_label("loot table", &table.name);
+            if table.drops.is_empty() {
+                self.error(&entry, "has no drops".to_string());
+            }
+            for drop in &table.drops {
+                if !self.entities.contains(drop.name.as_str()) {
+                    self.error(&entry, format!("drops unknown item \"{}\"", drop.name));
+                }
+                if drop.weight < 1 {
+                    self.error(&entry, format!("drop \"{}\" has no weight", drop.name));
+                }
+            }
+        }
+
+        for faction in &raws.faction_table {
+            let entry = entry_label("faction", &faction.name);
+            let mut others: Vec<_> = faction.responses.keys().collect();
+            others.sort();
+            for other in others {
+                let response = &faction.responses[other];
+                if other != "Default" && !self.factions.contains(other.as_str()) {
+                    self.error(&entry, format!("responds to unknown faction \"{other}\""));
+                }
+                if !["ignore", "flee", "attack"].contains(&response.as_str()) {
+                    self.error(
+                        &entry,
+                        format!(
+                            "response \"{response}\" to \"{other}\" is not ignore, flee or attack"
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn spells(&mut self, raws: &Raws) {
+        for spell in &raws.spells {
+            let entry = entry_label("spell", &spell.name);
+            if spell.mana_cost < 0 {
+                self.error(&entry, "mana_cost is negative".to_string());
+            }
+            self.effects(&entry, &spell.effects);
+        }
+        for weapon_trait in &raws.weapon_traits {
+            let entry = entry_label("weapon trait", &weapon_trait.name);
+            self.effects(&entry, &weapon_trait.effects);
+        }
+    }
+
+    fn achievements(&mut self, raws: &Raws) {
+        for achievement in &raws.achievements {
+            let entry = entry_label("achievement", &achievement.name);
+            if !EVENTS.contains(&achievement.event.as_str()) {
+                self.error(
+                    &entry,
+                    format!("counts unknown event \"{}\"", achievement.event),
+                );
+            }
+            if achievement.threshold < 1 {
+                self.error(&entry, "threshold must be at least 1".to_string());
+            }
+        }
+    }
+}
+
+// The names RawMaster::load generates from template_magic and weapon_traits.
+fn magic_item_names(raws: &Raws) -> Vec<String> {
+    let mut names = Vec::new();
+    for item in &raws.items {
+        let Some(template) = &item.template_magic else {
+            continue;
+        };
+        if template.include_cursed {
+            names.push(format!("{} -1", item.name));
+        }
+        for bonus in template.bonus_min..=template.bonus_max {
+            names.push(format!("{} +{bonus}", item.name));
+            if item.weapon.is_some() && bonus > 0 {
+                for weapon_trait in &raws.weapon_traits {
+                    names.push(format!("{} {} +{bonus}", weapon_trait.name, item.name));
+                }
+            }
+        }
+    }
+    names
+}
+
+// Every counter the game passes to gamelog::record_event.
+const EVENTS: [&str; 5] = [
+    "Turn",
+    "Kills",
+    "Level Up",
+    "Damage Taken",
+    "Damage Inflicted",
+];
+
+const SLOTS: [&str; 7] = ["Shield", "Head", "Torso", "Legs", "Feet", "Hands", "Melee"];
+
+// Checks everything the spawner would otherwise panic or quietly fall back on, so that a
+// broken raw file is reported in one go before the game starts. `sources` maps each entry to
+// the file it came from.
+#[must_use]
+pub(super) fn validate(raws: &Raws, sources: &HashMap<String, String>) -> Vec<RawError> {
+    let mut validator = Validator {
+        sources,
+        errors: Vec::new(),
+        entities: HashSet::new(),
+        spells: HashSet::new(),
+        loot_tables: HashSet::new(),
+        factions: HashSet::new(),
+    };
+
+    let magic_names = magic_item_names(raws);
+    let entities = raws
+        .items
+        .iter()
+        .map(|item| ("item", item.name.as_str()))
+        .chain(raws.mobs.iter().map(|mob| ("mob", mob.name.as_str())))
+        .chain(raws.props.iter().map(|prop| ("prop", prop.name.as_str())));
+    validator.entities = validator.unique(entities);
+    validator
+        .entities
+        .extend(magic_names.iter().map(String::as_str));
+    validator.spells = raws.spells.iter().map(|s| s.name.as_str()).collect();
+    validator.loot_tables = raws.loot_tables.iter().map(|l| l.name.as_str()).collect();
+    validator.factions = raws.faction_table.iter().map(|f| f.name.as_str()).collect();
+
+    validator.items(raws);
+    validator.mobs(raws);
+    validator.props(raws);
+    validator.tables(raws);
+    validator.spells(raws);
+    validator.achievements(raws);
+    validator.errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RawError;
+    use crate::raws::{embedded_raws, parse_raws, RawFile};
+    use serde_json::{json, Value};
+
+    // The built-in raws plus one extra file, and whatever is wrong with them.
+    fn errors_with(extra: &Value) -> Vec<RawError> {
+        let mut files = embedded_raws();
+        files.push(RawFile {
+            name: "test.json".to_string(),
+            data: extra.to_string(),
+        });
+        parse_raws(&files).err().unwrap_or_default()
+    }
+
+    fn rejected_with(extra: &Value, entry: &str, message: &str) {
+        let errors = errors_with(extra);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].file, "test.json");
+        assert_eq!(errors[0].entry, entry);
+        assert_eq!(errors[0].message, message);
+    }
+
+    fn sword(attribute: &str) -> Value {
+        json!({ "items": [{
+            "name": "Test Sword",
+            "renderable": { "glyph": "/", "fg": "#BB77BB", "bg": "#000000", "order": 2 },
+            "weapon": {
+                "range": "melee", "attribute": attribute, "base_damage": "1d8", "hit_bonus": 0
+            },
+        }] })
+    }
+
+    #[test]
+    fn the_built_in_raws_are_valid() {
+        let errors = errors_with(&json!({}));
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn weapon_attributes_must_match_exactly() {
+        assert!(errors_with(&sword("Might")).is_empty());
+        assert!(errors_with(&sword("Quickness")).is_empty());
+        for attribute in ["might", "quickness", "MIGHT", "Speed"] {
+            rejected_with(
+                &sword(attribute),
+                "item \"Test Sword\"",
+                &format!("weapon attribute \"{attribute}\" is not Might or Quickness"),
+            );
+        }
+    }
+
+    fn rat() -> Value {
+        json!({
+            "name": "Test Rat",
+            "renderable": { "glyph": "r", "fg": "#FF0000", "bg": "#000000", "order": 1 },
+            "blocks_tile": true,
+            "vision_range": 8,
+            "movement": "static",
+            "attributes": {},
+            "faction": "Hungry Rodents",
+        })
+    }
+
+    #[test]
+    fn a_mob_must_equip_items_that_exist() {
+        let mut rat = rat();
+        rat["equipped"] = json!(["Buckler"]);
+        rejected_with(
+            &json!({ "mobs": [rat] }),
+            "mob \"Test Rat\"",
+            "equips unknown item \"Buckler\"",
+        );
+    }
+
+    #[test]
+    fn a_mob_must_belong_to_a_known_faction() {
+        let mut rat = rat();
+        rat["faction"] = json!("Rat Kings");
+        rejected_with(
+            &json!({ "mobs": [rat] }),
+            "mob \"Test Rat\"",
+            "unknown faction \"Rat Kings\"",
+        );
+    }
+
+    #[test]
+    fn spawn_rows_must_name_something_that_exists() {
+        rejected_with(
+            &json!({ "spawn_table": [
+                { "name": "Unicorn", "weight": 1, "min_depth": 1, "max_depth": 5 }
+            ] }),
+            "spawn \"Unicorn at depths 1-5\"",
+            "not an item, mob or prop",
+        );
+        rejected_with(
+            &json!({ "spawn_table": [
+                { "name": "Rat", "weight": 1, "min_depth": 5, "max_depth": 1 }
+            ] }),
+            "spawn \"Rat at depths 5-1\"",
+            "min_depth is greater than max_depth",
+        );
+    }
+
+    #[test]
+    fn spells_must_cost_mana() {
+        rejected_with(
+            &json!({ "spells": [
+                { "name": "Freebie", "mana_cost": -1, "effects": [{ "damage": 1 }] }
+            ] }),
+            "spell \"Freebie\"",
+            "mana_cost is negative",
+        );
+    }
+
+    #[test]
+    fn achievements_must_count_a_known_event() {
+        rejected_with(
+            &json!({ "achievements": [
+                { "name": "Dancer", "event": "Dances", "threshold": 1, "description": "Dance." }
+            ] }),
+            "achievement \"Dancer\"",
+            "counts unknown event \"Dances\"",
+        );
+        rejected_with(
+            &json!({ "achievements": [
+                { "name": "Starter", "event": "Kills", "threshold": 0, "description": "Start." }
+            ] }),
+            "achievement \"Starter\"",
+            "threshold must be at least 1",
+        );
+    }
+}
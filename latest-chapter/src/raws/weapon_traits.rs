@@ -1,8 +1,8 @@
+use super::Effect;
 use serde::Deserialize;
-use std::collections::HashMap;
 
 #[derive(Deserialize, Debug)]
 pub struct WeaponTrait {
     pub name: String,
-    pub effects: HashMap<String, String>,
+    pub effects: Vec<Effect>,
 }
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
 #[derive(PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
 pub struct Rect {
@@ -28,8 +28,8 @@ impl Rect {
         ((self.x1 + self.x2) / 2, (self.y1 + self.y2) / 2)
     }
 
-    #[must_use] pub fn get_all_tiles(&self) -> HashSet<(i32, i32)> {
-        let mut result = HashSet::new();
+    #[must_use] pub fn get_all_tiles(&self) -> BTreeSet<(i32, i32)> {
+        let mut result = BTreeSet::new();
         for y in self.y1..self.y2 {
             for x in self.x1..self.x2 {
                 result.insert((x, y));
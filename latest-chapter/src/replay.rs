@@ -0,0 +1,210 @@
+use super::{command::Command, rng};
+use rltk::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_DELAY_MS: f32 = 100.0;
+const MAX_DELAY_MS: f32 = 1600.0;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    pub turn: u32,
+    pub command: Command,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub turn: u32,
+    pub commands: Vec<ReplayEntry>,
+}
+
+impl Replay {
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            turn: 0,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, command: Command, ends_turn: bool) {
+        self.commands.push(ReplayEntry {
+            turn: self.turn,
+            command,
+        });
+        if ends_turn {
+            self.turn += 1;
+        }
+    }
+
+    // Dice rolls aren't part of a save, so loading one reseeds the generator from the turn count.
+    // Replaying the reload then rolls exactly what the player saw.
+    #[must_use]
+    pub const fn reload_seed(&self) -> u64 {
+        rng::mix(self.seed, self.turn as u64)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_replay(_replay: &Replay, _path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Writes a recording to disk.
+///
+/// # Errors
+///
+/// Fails if the directory or the file can't be written.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_replay(replay: &Replay, path: &Path) -> io::Result<()> {
+    let data = serde_json::to_string(replay)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    crate::paths::write_atomically(path, data.as_bytes())
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    serde_json::from_str(&data).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Removes a recording, if there is one.
+///
+/// # Errors
+///
+/// Fails if the file is there but can't be removed.
+pub fn delete_replay(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[must_use]
+pub fn saved_replay(path: &Path) -> Option<Replay> {
+    if path.exists() {
+        load_replay(path).ok()
+    } else {
+        None
+    }
+}
+
+// Where the replay of the last game to end is kept.
+#[must_use]
+pub fn last_game_file() -> PathBuf {
+    crate::paths::data_dir().join("last_game.replay.json")
+}
+
+pub struct ReplayPlayer {
+    commands: Vec<ReplayEntry>,
+    next: usize,
+    timer: f32,
+    pub delay_ms: f32,
+    pub paused: bool,
+}
+
+impl ReplayPlayer {
+    #[must_use]
+    pub fn new(replay: &Replay) -> Self {
+        Self {
+            commands: replay.commands.clone(),
+            next: 0,
+            timer: 0.0,
+            delay_ms: DEFAULT_DELAY_MS,
+            paused: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_finished(&self) -> bool {
+        self.next >= self.commands.len()
+    }
+
+    #[must_use]
+    pub fn turn(&self) -> u32 {
+        self.commands
+            .get(self.next)
+            .or_else(|| self.commands.last())
+            .map_or(0, |entry| entry.turn)
+    }
+
+    #[must_use]
+    pub fn total_turns(&self) -> u32 {
+        self.commands.last().map_or(0, |entry| entry.turn)
+    }
+
+    // Space pauses, +/- change the speed and '.' steps one command at a time while paused.
+    // Returns the next command once it is due.
+    pub fn update(&mut self, key: Option<VirtualKeyCode>, frame_time_ms: f32) -> Option<Command> {
+        let mut step = false;
+        match key {
+            Some(VirtualKeyCode::Space) => self.paused = !self.paused,
+            Some(VirtualKeyCode::Equals | VirtualKeyCode::Plus | VirtualKeyCode::NumpadAdd) => {
+                self.delay_ms /= 2.0;
+            }
+            Some(VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract) => {
+                self.delay_ms = f32::min(f32::max(self.delay_ms, 1.0) * 2.0, MAX_DELAY_MS);
+            }
+            Some(VirtualKeyCode::Period) => step = true,
+            _ => {}
+        }
+
+        if self.is_finished() {
+            return None;
+        }
+        if self.paused {
+            if !step {
+                return None;
+            }
+        } else {
+            self.timer += frame_time_ms;
+            if self.timer < self.delay_ms {
+                return None;
+            }
+            self.timer = 0.0;
+        }
+
+        self.next += 1;
+        Some(self.commands[self.next - 1].command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Replay;
+    use crate::command::{Cheat, Command};
+    use crate::headless::{test_lock, Simulation};
+
+    #[test]
+    fn a_recording_replays_to_the_same_state() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(808);
+        for (dx, dy) in [(1, 1), (-1, 0), (0, 1), (1, -1)].repeat(8) {
+            sim.step(Command::Move { dx, dy });
+        }
+        sim.step(Command::PickUp);
+        sim.step(Command::Cheat {
+            cheat: Cheat::TeleportToExit,
+        });
+        sim.step(Command::Descend);
+        for (dx, dy) in [(0, -1), (-1, 0), (1, 0)].repeat(10) {
+            sim.step(Command::Move { dx, dy });
+        }
+        let recording = sim.state.recording.clone().expect("Nothing was recorded");
+
+        // Through the file format, as --replay would read it
+        let data = serde_json::to_string(&recording).expect("Unable to serialize replay");
+        let replay: Replay = serde_json::from_str(&data).expect("Unable to read replay");
+        let mut replayed = Simulation::new(replay.seed);
+        for entry in &replay.commands {
+            replayed.step(entry.command);
+        }
+        assert_eq!(replayed.depth(), 2);
+        assert!(replayed.fingerprint() == sim.fingerprint());
+    }
+}
@@ -8,6 +8,34 @@ pub fn reseed(seed: u64) {
     *RNG.lock().unwrap() = RandomNumberGenerator::seeded(seed);
 }
 
+// Picks a seed for a game where the player didn't ask for one. This deliberately doesn't touch
+// the shared generator, so it never disturbs a seeded run.
+#[must_use]
+pub fn random_seed() -> u64 {
+    RandomNumberGenerator::new().next_u64()
+}
+
+// Runs `f` with the shared generator temporarily replaced by one seeded with `seed`, then puts
+// the original generator back exactly as it was.
+pub fn with_seed<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    let previous = std::mem::replace(
+        &mut *RNG.lock().unwrap(),
+        RandomNumberGenerator::seeded(seed),
+    );
+    let result = f();
+    *RNG.lock().unwrap() = previous;
+    result
+}
+
+// SplitMix64 finaliser: turns a seed plus a salt into a well-spread derived seed.
+#[must_use]
+pub const fn mix(seed: u64, salt: u64) -> u64 {
+    let mut z = seed.wrapping_add(salt.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 #[must_use]
 pub fn roll_dice(n: i32, die_type: i32) -> i32 {
     RNG.lock().unwrap().roll_dice(n, die_type)
@@ -17,3 +45,32 @@ pub fn roll_dice(n: i32, die_type: i32) -> i32 {
 pub fn range(min: i32, max: i32) -> i32 {
     RNG.lock().unwrap().range(min, max)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::command::Command;
+    use crate::headless::{test_lock, Simulation};
+
+    fn play(seed: u64) -> (serde_json::Value, Vec<i32>) {
+        let mut sim = Simulation::new(seed);
+        for (dx, dy) in [(1, 0), (0, 1), (-1, 0), (0, -1)].repeat(10) {
+            sim.step(Command::Move { dx, dy });
+            sim.step(Command::Wait);
+        }
+        let rolls = (0..20).map(|_| super::roll_dice(1, 1000)).collect();
+        (sim.fingerprint(), rolls)
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_game() {
+        let _lock = test_lock();
+        let (first, first_rolls) = play(2024);
+        assert!(first["entities"].as_array().is_some_and(|e| e.len() > 1));
+        let (second, second_rolls) = play(2024);
+        assert!(first == second, "the map, spawns or player differ");
+        assert_eq!(first_rolls, second_rolls);
+
+        let (_, other_rolls) = play(2025);
+        assert_ne!(first_rolls, other_rolls);
+    }
+}
@@ -0,0 +1,221 @@
+use super::{gamelog, morgue::Killer, paths, Map, Pools};
+use crate::map::MasterDungeonMap;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub score: i32,
+    pub depth: i32,
+    pub level: i32,
+    pub gold: f32,
+    pub kills: i32,
+    pub turns: u32,
+    pub seed: u64,
+    pub killer: Option<String>,
+    pub ended_at: u64,
+    pub morgue: Option<PathBuf>,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum RunSort {
+    Score,
+    Depth,
+    Date,
+}
+
+impl RunSort {
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Score => Self::Depth,
+            Self::Depth => Self::Date,
+            Self::Date => Self::Score,
+        }
+    }
+
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Score => "score",
+            Self::Depth => "depth",
+            Self::Date => "date",
+        }
+    }
+}
+
+// Getting deeper counts for the most, then levels gained, then everything killed and collected.
+#[must_use]
+pub const fn score(depth: i32, level: i32, gold: f32, kills: i32) -> i32 {
+    depth * 1000 + level * 250 + kills * 20 + gold as i32
+}
+
+fn history_file() -> PathBuf {
+    paths::data_dir().join("runs.json")
+}
+
+#[must_use]
+pub fn finished_run(ecs: &World, turns: u32, morgue: Option<PathBuf>) -> RunRecord {
+    let player = *ecs.fetch::<Entity>();
+    let (level, gold) = ecs
+        .read_storage::<Pools>()
+        .get(player)
+        .map_or((1, 0.0), |pools| (pools.level, pools.gold));
+    let depth = ecs
+        .fetch::<MasterDungeonMap>()
+        .deepest_depth()
+        .max(ecs.fetch::<Map>().depth);
+    let kills = gamelog::get_event_count(&"Kills");
+    RunRecord {
+        score: score(depth, level, gold, kills),
+        depth,
+        level,
+        gold,
+        kills,
+        turns,
+        seed: ecs.fetch::<MasterDungeonMap>().seed,
+        killer: ecs.fetch::<Killer>().name.clone(),
+        ended_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        morgue,
+    }
+}
+
+// A missing or unreadable history is treated as empty rather than stopping the game.
+#[must_use]
+pub fn load_runs() -> Vec<RunRecord> {
+    fs::read_to_string(history_file())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Adds a finished run to the history file.
+///
+/// # Errors
+///
+/// Fails if the history can't be written.
+pub fn add_run(run: RunRecord) -> io::Result<()> {
+    let mut runs = load_runs();
+    runs.push(run);
+    let data = serde_json::to_string_pretty(&runs).expect("Unable to serialize run history");
+    fs::create_dir_all(paths::data_dir())?;
+    paths::write_atomically(&history_file(), data.as_bytes())
+}
+
+pub fn sort_runs(runs: &mut [RunRecord], sort: RunSort) {
+    match sort {
+        RunSort::Score => runs.sort_by_key(|run| Reverse(run.score)),
+        RunSort::Depth => runs.sort_by_key(|run| Reverse((run.depth, run.score))),
+        RunSort::Date => runs.sort_by_key(|run| Reverse(run.ended_at)),
+    }
+}
+
+// The run's morgue file if it's still around, otherwise what the history itself remembers.
+#[must_use]
+pub fn details(run: &RunRecord) -> Vec<String> {
+    if let Some(text) = run
+        .morgue
+        .as_ref()
+        .and_then(|file| fs::read_to_string(file).ok())
+    {
+        let mut lines = vec![format!("Score: {}", run.score), String::new()];
+        lines.extend(text.lines().map(str::to_string));
+        return lines;
+    }
+    vec![
+        format!(
+            "Killed by {} on depth {}.",
+            run.killer.as_deref().unwrap_or("unknown causes"),
+            run.depth
+        ),
+        format!("Score: {}", run.score),
+        format!(
+            "Level {}, {:.1} gold, {} kills",
+            run.level, run.gold, run.kills
+        ),
+        format!("Turns: {}", run.turns),
+        format!("World seed: {}", run.seed),
+        String::new(),
+        "The morgue file for this run is missing.".to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{details, finished_run, score, sort_runs, RunRecord, RunSort};
+    use crate::command::Command;
+    use crate::headless::{test_lock, Simulation};
+    use crate::morgue::Killer;
+
+    fn run(depth: i32, level: i32, kills: i32, ended_at: u64) -> RunRecord {
+        RunRecord {
+            score: score(depth, level, 0.0, kills),
+            depth,
+            level,
+            gold: 0.0,
+            kills,
+            turns: 0,
+            seed: 0,
+            killer: None,
+            ended_at,
+            morgue: None,
+        }
+    }
+
+    #[test]
+    fn getting_deeper_counts_for_the_most() {
+        assert!(score(2, 1, 0.0, 0) > score(1, 3, 100.0, 10));
+        assert!(score(1, 2, 0.0, 0) > score(1, 1, 200.0, 1));
+        assert!(score(1, 1, 0.0, 2) > score(1, 1, 30.0, 0));
+    }
+
+    #[test]
+    fn runs_sort_best_first() {
+        let mut runs = vec![
+            run(2, 1, 0, 30),
+            run(1, 12, 0, 10),
+            run(2, 1, 5, 20),
+            run(3, 1, 0, 0),
+        ];
+        let order = |runs: &[RunRecord]| runs.iter().map(|r| r.ended_at).collect::<Vec<_>>();
+
+        sort_runs(&mut runs, RunSort::Score);
+        assert_eq!(order(&runs), [10, 0, 20, 30]);
+        // Equally deep runs are ranked by score
+        sort_runs(&mut runs, RunSort::Depth);
+        assert_eq!(order(&runs), [0, 20, 30, 10]);
+        sort_runs(&mut runs, RunSort::Date);
+        assert_eq!(order(&runs), [30, 20, 10, 0]);
+    }
+
+    #[test]
+    fn a_finished_run_is_recorded_as_it_ended() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(42);
+        for _ in 0..5 {
+            sim.step(Command::Wait);
+        }
+        sim.state.ecs.fetch_mut::<Killer>().name = Some("Rat".to_string());
+
+        let record = finished_run(&sim.state.ecs, sim.turns(), None);
+        assert_eq!(
+            (
+                record.depth,
+                record.level,
+                record.kills,
+                record.turns,
+                record.seed
+            ),
+            (1, 1, 0, 5, 42)
+        );
+        assert_eq!(record.score, score(1, 1, record.gold, 0));
+        assert_eq!(details(&record)[0], "Killed by Rat on depth 1.");
+    }
+}
@@ -13,34 +13,446 @@ use super::components::{
     WantsToDropItem, WantsToFlee, WantsToMelee, WantsToPickupItem, WantsToRemoveItem, WantsToShoot,
     WantsToUseItem, Weapon, Wearable,
 };
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use specs::error::NoError;
 use specs::prelude::*;
 use specs::saveload::{
     DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
 };
 use std::fs;
-use std::fs::File;
-use std::path::Path;
-
-macro_rules! serialize_individually {
-    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
-        $(
-        SerializeComponents::<NoError, SimpleMarker<SerializeMe>>::serialize(
-            &( $ecs.read_storage::<$type>(), ),
-            &$data.0,
-            &$data.1,
-            &mut $ser,
-        )
-        .unwrap();
-        )*
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SAVE_SLOTS: usize = 5;
+// The autosave comes after the slots the player saves to.
+pub const AUTOSAVE_SLOT: usize = SAVE_SLOTS;
+
+// Where saves lived before there were slots.
+const LEGACY_SAVE_FILE: &str = "./savegame.json";
+const LEGACY_REPLAY_FILE: &str = "./savegame.replay.json";
+
+// Bump this whenever a saved component changes shape, and add a migration from the old shape.
+// Saves from before there was a header count as format 1.
+pub const SAVE_FORMAT: u32 = 3;
+
+// MIGRATIONS[n] upgrades the saved components from format n + 1 to n + 2.
+const MIGRATIONS: [fn(&mut Value); 2] = [add_dungeon_seed, fold_log_repeats];
+
+// How the components after the header are written. The header itself is always a line of JSON,
+// so the load screen can read it whatever the encoding.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveEncoding {
+    #[default]
+    Json,
+    // MessagePack, with struct fields named so old saves can still be migrated
+    Binary,
+    // Binary, then deflated
+    Compressed,
+}
+
+impl FromStr for SaveEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "binary" => Ok(Self::Binary),
+            "compressed" => Ok(Self::Compressed),
+            _ => Err(format!(
+                "Unknown save format [{s}], expected json, binary or compressed"
+            )),
+        }
+    }
+}
+
+// New saves use this; loading always goes by what the save's header says.
+static SAVE_ENCODING: Mutex<SaveEncoding> = Mutex::new(if cfg!(feature = "binary-saves") {
+    SaveEncoding::Compressed
+} else {
+    SaveEncoding::Json
+});
+
+pub fn set_save_encoding(encoding: SaveEncoding) {
+    *SAVE_ENCODING.lock().unwrap() = encoding;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveHeader {
+    pub format: u32,
+    pub game_version: String,
+    pub raws_hash: u64,
+    pub seed: u64,
+    #[serde(default)]
+    pub summary: SaveSummary,
+    #[serde(default)]
+    pub encoding: SaveEncoding,
+}
+
+// What the load screen shows for a slot. Saves from before slots have an empty one.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SaveSummary {
+    pub level: i32,
+    pub depth: i32,
+    pub map_name: String,
+    pub turns: u32,
+    pub play_time_secs: u64,
+    pub saved_at: u64,
+}
+
+impl SaveHeader {
+    fn current(ecs: &World, encoding: SaveEncoding, turns: u32, play_time_secs: u64) -> Self {
+        let player = *ecs.fetch::<Entity>();
+        let map = ecs.fetch::<super::map::Map>();
+        let summary = SaveSummary {
+            level: ecs
+                .read_storage::<Pools>()
+                .get(player)
+                .map_or(1, |pools| pools.level),
+            depth: map.depth,
+            map_name: map.name.clone(),
+            turns,
+            play_time_secs,
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+        };
+        Self {
+            format: SAVE_FORMAT,
+            game_version: env!("CARGO_PKG_VERSION").to_string(),
+            raws_hash: crate::raws::raws_hash(),
+            seed: ecs.fetch::<super::map::MasterDungeonMap>().seed,
+            summary,
+            encoding,
+        }
+    }
+
+    fn legacy() -> Self {
+        Self {
+            format: 1,
+            game_version: "unknown".to_string(),
+            raws_hash: 0,
+            seed: 0,
+            summary: SaveSummary::default(),
+            encoding: SaveEncoding::Json,
+        }
+    }
+}
+
+fn slot_name(slot: usize) -> String {
+    if slot == AUTOSAVE_SLOT {
+        "autosave".to_string()
+    } else {
+        format!("slot{}", slot + 1)
+    }
+}
+
+fn slot_file(slot: usize) -> PathBuf {
+    crate::paths::saves_dir().join(format!("{}.json", slot_name(slot)))
+}
+
+// Each slot keeps the replay of the game saved in it.
+#[must_use]
+pub fn slot_replay_file(slot: usize) -> PathBuf {
+    crate::paths::saves_dir().join(format!("{}.replay.json", slot_name(slot)))
+}
+
+// Present while the only record of the game in progress is the autosave. It is cleared when the
+// game is saved or ends, so finding it at startup means the last session crashed or was closed.
+fn unsaved_marker() -> PathBuf {
+    crate::paths::saves_dir().join("unsaved_progress")
+}
+
+pub fn set_unsaved_progress(unsaved: bool) {
+    if unsaved {
+        let _ = fs::write(unsaved_marker(), []);
+    } else if unsaved_marker().exists() {
+        let _ = fs::remove_file(unsaved_marker());
+    }
+}
+
+#[must_use]
+pub fn needs_recovery() -> bool {
+    unsaved_marker().exists() && slot_file(AUTOSAVE_SLOT).exists()
+}
+
+// Format 2: the dungeon map gained the world seed. Older games didn't have one, so use 0.
+fn add_dungeon_seed(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            if object.contains_key("identified_items") && object.contains_key("scroll_mappings") {
+                object.entry("seed").or_insert(Value::from(0));
+            }
+            object.values_mut().for_each(add_dungeon_seed);
+        }
+        Value::Array(array) => array.iter_mut().for_each(add_dungeon_seed),
+        _ => {}
+    }
+}
+
+// Format 3: the log is saved with repeated messages folded into a count, rather than written
+// out once per repeat.
+fn fold_log_repeats(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            if object.contains_key("events") {
+                if let Some(Value::Array(log)) = object.get_mut("log") {
+                    *log = fold_repeats(std::mem::take(log));
+                }
+            }
+            object.values_mut().for_each(fold_log_repeats);
+        }
+        Value::Array(array) => array.iter_mut().for_each(fold_log_repeats),
+        _ => {}
+    }
+}
+
+fn fold_repeats(messages: Vec<Value>) -> Vec<Value> {
+    let mut entries: Vec<Value> = Vec::new();
+    for fragments in messages {
+        match entries.last_mut() {
+            Some(last) if last["fragments"] == fragments => {
+                last["repeats"] = Value::from(last["repeats"].as_u64().unwrap_or(1) + 1);
+            }
+            _ => entries.push(serde_json::json!({ "fragments": fragments, "repeats": 1 })),
+        }
+    }
+    entries
+}
+
+// The one list of components. Saved components are written in the order given here, so new
+// ones go on the end and existing ones are never reordered. Transient ones are registered but
+// never saved.
+macro_rules! components {
+    (saved: [$($saved:ty),* $(,)?], transient: [$($transient:ty),* $(,)?] $(,)?) => {
+        pub fn register_components(ecs: &mut World) {
+            $( ecs.register::<$saved>(); )*
+            $( ecs.register::<$transient>(); )*
+        }
+
+        #[cfg(test)]
+        const SAVED_COMPONENTS: &[&str] = &[$( stringify!($saved) ),*];
+        #[cfg(test)]
+        const TRANSIENT_COMPONENTS: &[&str] = &[$( stringify!($transient) ),*];
+
+        fn serialize_components<S>(ecs: &World, serializer: &mut S)
+        where
+            for<'a> &'a mut S: serde::Serializer,
+        {
+            let data = (
+                ecs.entities(),
+                ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+            );
+            $(
+            SerializeComponents::<NoError, SimpleMarker<SerializeMe>>::serialize(
+                &( ecs.read_storage::<$saved>(), ),
+                &data.0,
+                &data.1,
+                &mut *serializer,
+            )
+            .unwrap();
+            )*
+        }
+
+        fn deserialize_components<'de, D>(ecs: &World, de: &mut D) -> Result<(), String>
+        where
+            for<'a> &'a mut D: serde::Deserializer<'de>,
+        {
+            let mut data = (
+                &mut ecs.entities(),
+                &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(),
+                &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
+            );
+            $(
+            DeserializeComponents::<NoError, _>::deserialize(
+                &mut ( &mut ecs.write_storage::<$saved>(), ),
+                &data.0, // entities
+                &mut data.1, // marker
+                &mut data.2, // allocater
+                &mut *de,
+            )
+            .map_err(|e| format!("{}: {e}", stringify!($saved)))?;
+            )*
+            Ok(())
+        }
     };
 }
 
+components!(
+    saved: [
+        Position,
+        Renderable,
+        Player,
+        Viewshed,
+        Name,
+        BlocksTile,
+        WantsToMelee,
+        Item,
+        Consumable,
+        Ranged,
+        InflictsDamage,
+        AreaOfEffect,
+        Confusion,
+        ProvidesHealing,
+        InBackpack,
+        WantsToPickupItem,
+        WantsToUseItem,
+        WantsToDropItem,
+        SerializationHelper,
+        Equippable,
+        Equipped,
+        Weapon,
+        Wearable,
+        WantsToRemoveItem,
+        ParticleLifetime,
+        HungerClock,
+        ProvidesFood,
+        MagicMapper,
+        Hidden,
+        EntryTrigger,
+        EntityMoved,
+        SingleActivation,
+        BlocksVisibility,
+        Door,
+        Quips,
+        Attributes,
+        Skills,
+        Pools,
+        NaturalAttackDefense,
+        LootTable,
+        OtherLevelPosition,
+        DMSerializationHelper,
+        LightSource,
+        Initiative,
+        MyTurn,
+        Faction,
+        WantsToApproach,
+        WantsToFlee,
+        MoveMode,
+        Chasing,
+        EquipmentChanged,
+        Vendor,
+        TownPortal,
+        TeleportTo,
+        ApplyMove,
+        ApplyTeleport,
+        MagicItem,
+        ObfuscatedName,
+        IdentifiedItem,
+        SpawnParticleBurst,
+        SpawnParticleLine,
+        CursedItem,
+        ProvidesRemoveCurse,
+        ProvidesIdentification,
+        AttributeBonus,
+        StatusEffect,
+        Duration,
+        KnownSpells,
+        SpellTemplate,
+        WantsToCastSpell,
+        TeachesSpell,
+        ProvidesMana,
+        Slow,
+        DamageOverTime,
+        SpecialAbilities,
+        TileSize,
+        OnDeath,
+        AlwaysTargetsSelf,
+        Target,
+        WantsToShoot,
+    ],
+    transient: [SimpleMarker<SerializeMe>],
+);
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_game(
+    _ecs: &mut World,
+    _slot: usize,
+    _turns: u32,
+    _play_time_secs: u64,
+) -> io::Result<()> {
+    Ok(())
+}
+
+/// Writes the game to a save slot.
+///
+/// # Errors
+///
+/// Fails if the save directory or the save file can't be written.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_game(ecs: &mut World, slot: usize, turns: u32, play_time_secs: u64) -> io::Result<()> {
+    let encoding = *SAVE_ENCODING.lock().unwrap();
+    let header = SaveHeader::current(ecs, encoding, turns, play_time_secs);
+    let body = serialize_world(ecs, encoding);
+    write_save(slot, &header, &body)
+}
+
+// The game carries on from the autosave it has just written, exactly as `Command::Reload` would,
+// so it has to be recorded as a reload to keep replays in step.
 #[cfg(target_arch = "wasm32")]
-pub fn save_game(_ecs: &mut World) {}
+pub fn autosave(ecs: &mut World, _turns: u32, _play_time_secs: u64) -> io::Result<()> {
+    reload_game(ecs);
+    Ok(())
+}
 
+/// Writes the autosave slot and carries on from it. The reload happens even if the write
+/// fails, so a failed autosave still plays the same as a good one.
+///
+/// # Errors
+///
+/// Fails if the save directory or the save file can't be written.
 #[cfg(not(target_arch = "wasm32"))]
-pub fn save_game(ecs: &mut World) {
+pub fn autosave(ecs: &mut World, turns: u32, play_time_secs: u64) -> io::Result<()> {
+    let encoding = *SAVE_ENCODING.lock().unwrap();
+    let header = SaveHeader::current(ecs, encoding, turns, play_time_secs);
+    let body = serialize_world(ecs, encoding);
+    let result = write_save(AUTOSAVE_SLOT, &header, &body);
+    if result.is_ok() {
+        set_unsaved_progress(true);
+    }
+    reload_from(ecs, &body, encoding);
+    result
+}
+
+fn write_save(slot: usize, header: &SaveHeader, body: &[u8]) -> io::Result<()> {
+    let mut data = serde_json::to_vec(header)?;
+    data.push(b'\n');
+    data.extend_from_slice(body);
+    fs::create_dir_all(crate::paths::saves_dir())?;
+    crate::paths::write_atomically(&slot_file(slot), &data)
+}
+
+// A save and reload in one go, as if the game had been saved and the save loaded straight back.
+pub fn reload_game(ecs: &mut World) {
+    let data = serialize_world(ecs, SaveEncoding::Json);
+    reload_from(ecs, &data, SaveEncoding::Json);
+}
+
+fn reload_from(ecs: &mut World, data: &[u8], encoding: SaveEncoding) {
+    *ecs = crate::new_world();
+    deserialize_world(ecs, data, encoding).expect("Unable to reload the game");
+}
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).expect("Unable to compress save");
+    encoder.finish().expect("Unable to compress save")
+}
+
+fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut result = Vec::new();
+    flate2::read::DeflateDecoder::new(data)
+        .read_to_end(&mut result)
+        .map_err(|e| format!("decompressing: {e}"))?;
+    Ok(result)
+}
+
+fn serialize_world(ecs: &mut World, encoding: SaveEncoding) -> Vec<u8> {
     // Create helper
     let mapcopy = ecs.get_mut::<super::map::Map>().unwrap().clone();
     let dungeon_master = ecs
@@ -63,127 +475,197 @@ pub fn save_game(ecs: &mut World) {
         .build();
 
     // Actually serialize
-    {
-        let data = (
-            ecs.entities(),
-            ecs.read_storage::<SimpleMarker<SerializeMe>>(),
-        );
-
-        let writer = File::create("./savegame.json").unwrap();
-        let mut serializer = serde_json::Serializer::new(writer);
-        serialize_individually!(
-            ecs,
-            serializer,
-            data,
-            Position,
-            Renderable,
-            Player,
-            Viewshed,
-            Name,
-            BlocksTile,
-            WantsToMelee,
-            Item,
-            Consumable,
-            Ranged,
-            InflictsDamage,
-            AreaOfEffect,
-            Confusion,
-            ProvidesHealing,
-            InBackpack,
-            WantsToPickupItem,
-            WantsToUseItem,
-            WantsToDropItem,
-            SerializationHelper,
-            Equippable,
-            Equipped,
-            Weapon,
-            Wearable,
-            WantsToRemoveItem,
-            ParticleLifetime,
-            HungerClock,
-            ProvidesFood,
-            MagicMapper,
-            Hidden,
-            EntryTrigger,
-            EntityMoved,
-            SingleActivation,
-            BlocksVisibility,
-            Door,
-            Quips,
-            Attributes,
-            Skills,
-            Pools,
-            NaturalAttackDefense,
-            LootTable,
-            OtherLevelPosition,
-            DMSerializationHelper,
-            LightSource,
-            Initiative,
-            MyTurn,
-            Faction,
-            WantsToApproach,
-            WantsToFlee,
-            MoveMode,
-            Chasing,
-            EquipmentChanged,
-            Vendor,
-            TownPortal,
-            TeleportTo,
-            ApplyMove,
-            ApplyTeleport,
-            MagicItem,
-            ObfuscatedName,
-            IdentifiedItem,
-            SpawnParticleBurst,
-            SpawnParticleLine,
-            CursedItem,
-            ProvidesRemoveCurse,
-            ProvidesIdentification,
-            AttributeBonus,
-            StatusEffect,
-            Duration,
-            KnownSpells,
-            SpellTemplate,
-            WantsToCastSpell,
-            TeachesSpell,
-            ProvidesMana,
-            Slow,
-            DamageOverTime,
-            SpecialAbilities,
-            TileSize,
-            OnDeath,
-            AlwaysTargetsSelf,
-            Target,
-            WantsToShoot
-        );
+    let mut writer = Vec::new();
+    match encoding {
+        SaveEncoding::Json => {
+            serialize_components(ecs, &mut serde_json::Serializer::new(&mut writer));
+        }
+        SaveEncoding::Binary | SaveEncoding::Compressed => {
+            serialize_components(
+                ecs,
+                &mut rmp_serde::Serializer::new(&mut writer).with_struct_map(),
+            );
+        }
     }
 
     // Clean up
     ecs.delete_entity(savehelper).expect("Crash on cleanup");
     ecs.delete_entity(savehelper2).expect("Crash on cleanup");
+    if encoding == SaveEncoding::Compressed {
+        compress(&writer)
+    } else {
+        writer
+    }
+}
+
+// The header of the save in each slot, autosave included, without reading the rest of it.
+#[must_use]
+pub fn save_slots() -> Vec<Option<SaveHeader>> {
+    (0..=AUTOSAVE_SLOT)
+        .map(|slot| {
+            let path = slot_file(slot);
+            let file = fs::File::open(&path)
+                .or_else(|_| fs::File::open(crate::paths::backup_file(&path)))
+                .ok()?;
+            let mut first_line = String::new();
+            BufReader::new(file).read_line(&mut first_line).ok()?;
+            Some(serde_json::from_str(&first_line).unwrap_or_else(|_| SaveHeader::legacy()))
+        })
+        .collect()
 }
 
 #[must_use]
 pub fn does_save_exist() -> bool {
-    Path::new("./savegame.json").exists()
-}
-
-macro_rules! deserialize_individually {
-    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
-        $(
-        DeserializeComponents::<NoError, _>::deserialize(
-            &mut ( &mut $ecs.write_storage::<$type>(), ),
-            &$data.0, // entities
-            &mut $data.1, // marker
-            &mut $data.2, // allocater
-            &mut $de,
-        )
-        .unwrap();
-        )*
+    (0..=AUTOSAVE_SLOT).any(|slot| {
+        let path = slot_file(slot);
+        path.exists() || crate::paths::backup_file(&path).exists()
+    })
+}
+
+// Moves a save left in the working directory by an older build into the first free slot.
+pub fn import_legacy_save() {
+    if !Path::new(LEGACY_SAVE_FILE).exists() {
+        return;
+    }
+    let Some(slot) = save_slots()[..SAVE_SLOTS].iter().position(Option::is_none) else {
+        return;
+    };
+    let moved = fs::create_dir_all(crate::paths::saves_dir())
+        .and_then(|()| move_file(Path::new(LEGACY_SAVE_FILE), &slot_file(slot)));
+    match moved {
+        Ok(()) => {
+            if Path::new(LEGACY_REPLAY_FILE).exists() {
+                let _ = move_file(Path::new(LEGACY_REPLAY_FILE), &slot_replay_file(slot));
+            }
+        }
+        Err(e) => rltk::console::log(format!("Unable to move {LEGACY_SAVE_FILE}: {e}")),
+    }
+}
+
+// A rename won't work if the data directory is on another drive.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::rename(from, to).or_else(|_| fs::copy(from, to).and_then(|_| fs::remove_file(from)))
+}
+
+// Splits off the header and brings older saves up to date. Anything that can't be loaded comes
+// back as a message for the player.
+fn upgrade_save(data: &[u8]) -> Result<(SaveHeader, Vec<u8>), String> {
+    let (first_line, rest) = data
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or((data, &[][..]), |i| (&data[..i], &data[i + 1..]));
+    let (mut header, body) = serde_json::from_slice::<SaveHeader>(first_line)
+        .map_or_else(|_| (SaveHeader::legacy(), data), |header| (header, rest));
+
+    if header.format > SAVE_FORMAT {
+        return Err(format!(
+            "This save is from a newer version of the game ({}).",
+            header.game_version
+        ));
+    }
+    if header.format == SAVE_FORMAT {
+        return Ok((header, body.to_vec()));
+    }
+
+    // Migrations work on JSON, so binary saves are converted first and load as JSON afterwards
+    let mut values = match header.encoding {
+        SaveEncoding::Json => serde_json::Deserializer::from_slice(body)
+            .into_iter::<Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(damaged)?,
+        SaveEncoding::Binary => binary_to_json(body).map_err(damaged)?,
+        SaveEncoding::Compressed => {
+            binary_to_json(&decompress(body).map_err(damaged)?).map_err(damaged)?
+        }
+    };
+    for migration in &MIGRATIONS[header.format as usize - 1..] {
+        values.iter_mut().for_each(migration);
+    }
+    let body = values
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+    header.encoding = SaveEncoding::Json;
+    Ok((header, body.into_bytes()))
+}
+
+fn damaged<E>(_: E) -> String {
+    "This save file is damaged.".to_string()
+}
+
+fn binary_to_json(mut data: &[u8]) -> Result<Vec<Value>, rmpv::decode::Error> {
+    let mut values = Vec::new();
+    while !data.is_empty() {
+        values.push(msgpack_to_json(rmpv::decode::read_value(&mut data)?));
+    }
+    Ok(values)
+}
+
+// JSON only has string keys, so other keys are written the way serde_json writes them.
+fn msgpack_to_json(value: rmpv::Value) -> Value {
+    match value {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(b) => Value::Bool(b),
+        rmpv::Value::Integer(n) => n
+            .as_i64()
+            .map_or_else(|| Value::from(n.as_u64()), Value::from),
+        rmpv::Value::F32(n) => Value::from(n),
+        rmpv::Value::F64(n) => Value::from(n),
+        rmpv::Value::String(s) => Value::from(s.into_str()),
+        rmpv::Value::Binary(bytes) | rmpv::Value::Ext(_, bytes) => Value::from(bytes),
+        rmpv::Value::Array(array) => array.into_iter().map(msgpack_to_json).collect(),
+        rmpv::Value::Map(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let key = match msgpack_to_json(key) {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    (key, msgpack_to_json(value))
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Loads the save in `slot` into `ecs`, upgrading it first if it is from an older build. If the
+/// save itself won't load, the backup kept from the save before it is tried instead.
+///
+/// # Errors
+///
+/// Returns a message for the player if the save is unreadable, damaged or too new.
+pub fn load_game(ecs: &mut World, slot: usize) -> Result<SaveHeader, String> {
+    let path = slot_file(slot);
+    let message = match load_file(ecs, &path) {
+        Ok(header) => return Ok(header),
+        Err(message) => message,
     };
+
+    let backup = crate::paths::backup_file(&path);
+    if !backup.exists() {
+        return Err(message);
+    }
+    *ecs = crate::new_world();
+    let header = load_file(ecs, &backup).map_err(|_| message)?;
+    crate::gamelog::Logger::new()
+        .color(rltk::YELLOW)
+        .append(&"The save was damaged, so the one before it was loaded instead.")
+        .log();
+    Ok(header)
+}
+
+fn load_file(ecs: &mut World, path: &Path) -> Result<SaveHeader, String> {
+    let data = fs::read(path).map_err(|e| format!("Unable to read the save: {e}"))?;
+    let (header, body) = upgrade_save(&data)?;
+    deserialize_world(ecs, &body, header.encoding).map_err(|e| {
+        rltk::console::log(format!("Failed to load save: {e}"));
+        "This save can't be loaded by this version of the game.".to_string()
+    })?;
+    Ok(header)
 }
 
-pub fn load_game(ecs: &mut World) {
+fn deserialize_world(ecs: &mut World, data: &[u8], encoding: SaveEncoding) -> Result<(), String> {
     {
         // Delete everything
         let mut to_delete = Vec::new();
@@ -195,101 +677,17 @@ pub fn load_game(ecs: &mut World) {
         }
     }
 
-    let data = fs::read_to_string("./savegame.json").unwrap();
-    let mut de = serde_json::Deserializer::from_str(&data);
-
-    {
-        let mut d = (
-            &mut ecs.entities(),
-            &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(),
-            &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
-        );
-
-        deserialize_individually!(
-            ecs,
-            de,
-            d,
-            Position,
-            Renderable,
-            Player,
-            Viewshed,
-            Name,
-            BlocksTile,
-            WantsToMelee,
-            Item,
-            Consumable,
-            Ranged,
-            InflictsDamage,
-            AreaOfEffect,
-            Confusion,
-            ProvidesHealing,
-            InBackpack,
-            WantsToPickupItem,
-            WantsToUseItem,
-            WantsToDropItem,
-            SerializationHelper,
-            Equippable,
-            Equipped,
-            Weapon,
-            Wearable,
-            WantsToRemoveItem,
-            ParticleLifetime,
-            HungerClock,
-            ProvidesFood,
-            MagicMapper,
-            Hidden,
-            EntryTrigger,
-            EntityMoved,
-            SingleActivation,
-            BlocksVisibility,
-            Door,
-            Quips,
-            Attributes,
-            Skills,
-            Pools,
-            NaturalAttackDefense,
-            LootTable,
-            OtherLevelPosition,
-            DMSerializationHelper,
-            LightSource,
-            Initiative,
-            MyTurn,
-            Faction,
-            WantsToApproach,
-            WantsToFlee,
-            MoveMode,
-            Chasing,
-            EquipmentChanged,
-            Vendor,
-            TownPortal,
-            TeleportTo,
-            ApplyMove,
-            ApplyTeleport,
-            MagicItem,
-            ObfuscatedName,
-            IdentifiedItem,
-            SpawnParticleBurst,
-            SpawnParticleLine,
-            CursedItem,
-            ProvidesRemoveCurse,
-            ProvidesIdentification,
-            AttributeBonus,
-            StatusEffect,
-            Duration,
-            KnownSpells,
-            SpellTemplate,
-            WantsToCastSpell,
-            TeachesSpell,
-            ProvidesMana,
-            Slow,
-            DamageOverTime,
-            SpecialAbilities,
-            TileSize,
-            OnDeath,
-            AlwaysTargetsSelf,
-            Target,
-            WantsToShoot
-        );
+    match encoding {
+        SaveEncoding::Json => {
+            deserialize_components(ecs, &mut serde_json::Deserializer::from_slice(data))?;
+        }
+        SaveEncoding::Binary => {
+            deserialize_components(ecs, &mut rmp_serde::Deserializer::from_read_ref(data))?;
+        }
+        SaveEncoding::Compressed => {
+            let data = decompress(data)?;
+            deserialize_components(ecs, &mut rmp_serde::Deserializer::from_read_ref(&data))?;
+        }
     }
 
     let mut deleteme: Option<Entity> = None;
@@ -310,7 +708,7 @@ pub fn load_game(ecs: &mut World) {
             let mut dungeonmaster = ecs.write_resource::<super::map::MasterDungeonMap>();
             *dungeonmaster = h.map.clone();
             deleteme2 = Some(e);
-            crate::gamelog::restore_log(&mut h.log.clone());
+            crate::gamelog::restore_log(h.log.clone());
             crate::gamelog::load_events(&h.events.clone());
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
@@ -320,14 +718,149 @@ pub fn load_game(ecs: &mut World) {
             *player_resource = e;
         }
     }
-    ecs.delete_entity(deleteme.unwrap())
+    ecs.delete_entity(deleteme.ok_or("the map is missing")?)
         .expect("Unable to delete helper");
-    ecs.delete_entity(deleteme2.unwrap())
+    ecs.delete_entity(deleteme2.ok_or("the dungeon is missing")?)
         .expect("Unable to delete helper");
+    Ok(())
 }
 
-pub fn delete_save() {
-    if Path::new("./savegame.json").exists() {
-        std::fs::remove_file("./savegame.json").expect("Unable to delete file");
+#[cfg(test)]
+mod tests {
+    use super::{
+        deserialize_world, fold_log_repeats, serialize_world, SaveEncoding, SAVED_COMPONENTS,
+        TRANSIENT_COMPONENTS,
+    };
+    use crate::command::Command;
+    use crate::headless::Simulation;
+    use serde_json::Value;
+    use specs::World;
+
+    fn load(data: &[u8], encoding: SaveEncoding) -> World {
+        let mut ecs = crate::new_world();
+        deserialize_world(&mut ecs, data, encoding).expect("Unable to load");
+        ecs
+    }
+
+    // Hash sets come out in whatever order they iterate in.
+    const SETS: [&str; 3] = ["bloodstains", "view_blocked", "identified_items"];
+
+    fn sort_sets(value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    if let (true, Value::Array(array)) = (SETS.contains(&key.as_str()), &mut *value)
+                    {
+                        array.sort_by_key(Value::to_string);
+                    }
+                    sort_sets(value);
+                }
+            }
+            Value::Array(array) => array.iter_mut().for_each(sort_sets),
+            _ => {}
+        }
+    }
+
+    // Compared as values, since hash maps don't serialize in any particular order either.
+    fn as_values(data: &[u8]) -> Vec<Value> {
+        let mut values = serde_json::Deserializer::from_slice(data)
+            .into_iter::<Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Not JSON");
+        values.iter_mut().for_each(sort_sets);
+        values
+    }
+
+    #[test]
+    fn every_encoding_restores_the_same_world() {
+        let _lock = crate::headless::test_lock();
+        let mut sim = Simulation::new(1234);
+        for _ in 0..20 {
+            sim.step(Command::Move { dx: 1, dy: 0 });
+            sim.step(Command::Move { dx: 0, dy: 1 });
+        }
+        let original = serialize_world(&mut sim.state.ecs, SaveEncoding::Json);
+
+        // Saving hands out fresh markers, so each encoding starts from an identical copy
+        let saved_as =
+            |encoding| serialize_world(&mut load(&original, SaveEncoding::Json), encoding);
+        let json = saved_as(SaveEncoding::Json);
+        let expected = as_values(&serialize_world(
+            &mut load(&json, SaveEncoding::Json),
+            SaveEncoding::Json,
+        ));
+
+        for encoding in [SaveEncoding::Binary, SaveEncoding::Compressed] {
+            let data = saved_as(encoding);
+            assert!(
+                data.len() < json.len(),
+                "{encoding:?} is no smaller than JSON"
+            );
+            let restored = serialize_world(&mut load(&data, encoding), SaveEncoding::Json);
+            assert!(
+                as_values(&restored) == expected,
+                "{encoding:?} restored a different world"
+            );
+        }
+    }
+
+    #[test]
+    fn older_saves_have_their_log_repeats_folded() {
+        let wait =
+            serde_json::json!([{ "color": { "r": 1.0, "g": 1.0, "b": 1.0 }, "text": "You wait" }]);
+        let bite = serde_json::json!([{ "color": { "r": 1.0, "g": 0.0, "b": 0.0 }, "text": "The rat bites" }]);
+        let mut helper = serde_json::json!({
+            "log": [wait, wait, wait, bite, wait],
+            "events": {},
+        });
+        fold_log_repeats(&mut helper);
+        assert_eq!(
+            helper["log"],
+            serde_json::json!([
+                { "fragments": wait, "repeats": 3 },
+                { "fragments": bite, "repeats": 1 },
+                { "fragments": wait, "repeats": 1 },
+            ])
+        );
+    }
+
+    // Every type deriving Component must be in the registry, either saved or transient.
+    #[test]
+    fn every_component_is_registered() {
+        let source = include_str!("components.rs");
+        let mut lines = source.lines();
+        let mut found = 0;
+        let mut missing = Vec::new();
+        while let Some(line) = lines.next() {
+            if !(line.starts_with("#[derive(") && line.contains("Component")) {
+                continue;
+            }
+            // The name follows `struct` or `enum`, whatever visibility or generics surround it
+            let Some(name) = lines.find(|line| !line.starts_with("#[")).and_then(|line| {
+                let mut words = line
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .filter(|word| !word.is_empty());
+                words.find(|word| *word == "struct" || *word == "enum")?;
+                words.next()
+            }) else {
+                continue;
+            };
+            found += 1;
+            if !SAVED_COMPONENTS.contains(&name) && !TRANSIENT_COMPONENTS.contains(&name) {
+                missing.push(name.to_string());
+            }
+        }
+        // A change in layout that the scan can't read shouldn't pass as nothing missing
+        assert!(
+            found >= SAVED_COMPONENTS.len(),
+            "only found {found} components"
+        );
+        assert!(
+            missing.is_empty(),
+            "not in the component registry: {missing:?}"
+        );
+        assert!(TRANSIENT_COMPONENTS
+            .iter()
+            .all(|c| !SAVED_COMPONENTS.contains(c)));
     }
 }
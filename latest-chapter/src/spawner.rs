@@ -10,14 +10,14 @@ use crate::{attr_bonus, mana_at_level, player_hp_at_level};
 use rltk::RGB;
 use specs::prelude::*;
 use specs::saveload::{MarkedBuilder, SimpleMarker};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// Spawns the player and returns his/her entity object.
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
     spawn_all_spells(ecs);
 
     let mut skills = Skills {
-        skills: HashMap::new(),
+        skills: BTreeMap::new(),
     };
     skills.skills.insert(Skill::Melee, 1);
     skills.skills.insert(Skill::Defense, 1);
@@ -195,7 +195,7 @@ pub fn spawn_region(
     spawn_list: &mut Vec<(usize, String)>,
 ) {
     let spawn_table = room_table(map_depth);
-    let mut spawn_points: HashMap<usize, String> = HashMap::new();
+    let mut spawn_points: BTreeMap<usize, String> = BTreeMap::new();
     let mut areas: Vec<usize> = Vec::from(area);
 
     // Scope to keep the borrow checker happy
@@ -1,17 +1,11 @@
-#[cfg(target_arch = "wasm32")]
+// Systems always run one after another, in the order listed below. Running them in parallel
+// lets dice rolls and queued effects interleave differently on every run, which would break
+// seeded games and replays.
 #[macro_use]
 mod single_thread;
 
-#[cfg(not(target_arch = "wasm32"))]
-#[macro_use]
-mod multi_thread;
-
-#[cfg(target_arch = "wasm32")]
 pub use single_thread::*;
 
-#[cfg(not(target_arch = "wasm32"))]
-pub use multi_thread::*;
-
 use super::{AdjacentAI, ApproachAI, ChaseAI, DefaultMoveAI, EncumbranceSystem, FleeAI, HungerSystem, InitiativeSystem, ItemCollectionSystem, ItemDropSystem, ItemEquipOnUse, ItemIdentificationSystem, ItemRemoveSystem, ItemUseSystem, LightingSystem, MapIndexingSystem, MeleeCombatSystem, MovementSystem, ParticleSpawnSystem, QuipSystem, RangedCombatSystem, SpellUseSystem, TriggerSystem, TurnStatusSystem, VisibilitySystem, VisibleAI};
 use specs::prelude::World;
 
@@ -1,4 +1,3 @@
-use super::super::*;
 use super::UnifiedDispatcher;
 use specs::prelude::*;
 
@@ -30,10 +29,10 @@ pub struct SingleThreadedDispatcher<'a> {
     pub systems: Vec<Box<dyn RunNow<'a>>>,
 }
 
-impl<'a> UnifiedDispatcher for SingleThreadedDispatcher<'a> {
+impl UnifiedDispatcher for SingleThreadedDispatcher<'_> {
     fn run_now(&mut self, ecs: *mut World) {
         unsafe {
-            for sys in self.systems.iter_mut() {
+            for sys in &mut self.systems {
                 sys.run_now(&*ecs);
             }
             crate::effects::run_effects_queue(&mut *ecs);
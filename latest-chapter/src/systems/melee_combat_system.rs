@@ -1,5 +1,8 @@
 use crate::{
     effects::{add_effect, EffectType, Targets},
+    event_bus::{
+        self, ArmorClass, AttackKind, AttackOutcome, DamageRoll, GameEvent, HitRoll, Participant,
+    },
     skill_bonus, Attributes, EquipmentSlot, Equipped, HungerClock, HungerState, Name,
     NaturalAttackDefense, Pools, Skill, Skills, WantsToMelee, Weapon, WeaponAttribute, Wearable,
 };
@@ -7,6 +10,85 @@ use specs::prelude::*;
 
 pub struct MeleeCombatSystem {}
 
+// The basic unarmed attack, used when nothing better is wielded.
+#[must_use]
+pub const fn unarmed_attack() -> Weapon {
+    Weapon {
+        range: None,
+        attribute: WeaponAttribute::Might,
+        hit_bonus: 0,
+        damage_n_dice: 1,
+        damage_die_type: 4,
+        damage_bonus: 0,
+        proc_chance: None,
+        proc_target: None,
+    }
+}
+
+// Everything added to the d20 when attacking with `weapon`. The natural roll is left at zero, so
+// `total` is the bonus alone until `HitRoll::with_roll` adds the die.
+#[must_use]
+pub fn melee_hit_bonus(
+    weapon: &Weapon,
+    attributes: &Attributes,
+    skills: &Skills,
+    hunger: Option<&HungerClock>,
+) -> HitRoll {
+    let attribute_bonus = if weapon.attribute == WeaponAttribute::Might {
+        attributes.might.bonus
+    } else {
+        attributes.quickness.bonus
+    };
+    let skill_bonus = skill_bonus(Skill::Melee, skills);
+    let weapon_bonus = weapon.hit_bonus;
+    let mut status_bonus = 0;
+    if let Some(hc) = hunger {
+        // Well-Fed grants +1
+        if hc.state == HungerState::WellFed {
+            status_bonus += 1;
+        }
+    }
+    HitRoll {
+        natural_roll: 0,
+        attribute_bonus,
+        skill_bonus,
+        weapon_bonus,
+        status_bonus,
+        total: attribute_bonus + skill_bonus + weapon_bonus + status_bonus,
+    }
+}
+
+// The armour class an attacker has to beat to hit `target`.
+#[must_use]
+pub fn armor_class(
+    target: Entity,
+    attributes: &Attributes,
+    skills: &Skills,
+    equipped_items: &ReadStorage<Equipped>,
+    wearables: &ReadStorage<Wearable>,
+    natural: &ReadStorage<NaturalAttackDefense>,
+) -> ArmorClass {
+    let mut armor_item_bonus_f = 0.0;
+    for (wielded, armor) in (equipped_items, wearables).join() {
+        if wielded.owner == target {
+            armor_item_bonus_f += armor.armor_class;
+        }
+    }
+    let base = natural
+        .get(target)
+        .map_or(10, |nat| nat.armor_class.unwrap_or(10));
+    let quickness_bonus = attributes.quickness.bonus;
+    let skill_bonus = skill_bonus(Skill::Defense, skills);
+    let item_bonus = armor_item_bonus_f as i32;
+    ArmorClass {
+        base,
+        quickness_bonus,
+        skill_bonus,
+        item_bonus,
+        total: base + quickness_bonus + skill_bonus + item_bonus,
+    }
+}
+
 impl<'a> System<'a> for MeleeCombatSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
@@ -56,16 +138,7 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 let target_name = names.get(wants_melee.target).unwrap();
 
                 // Define the basic unarmed attack - overridden by wielding check below if a weapon is equipped
-                let mut weapon_info = Weapon {
-                    range: None,
-                    attribute: WeaponAttribute::Might,
-                    hit_bonus: 0,
-                    damage_n_dice: 1,
-                    damage_die_type: 4,
-                    damage_bonus: 0,
-                    proc_chance: None,
-                    proc_target: None,
-                };
+                let mut weapon_info = unarmed_attack();
 
                 if let Some(nat) = natural.get(entity) {
                     if !nat.attacks.is_empty() {
@@ -90,44 +163,29 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 }
 
                 let natural_roll = crate::rng::roll_dice(1, 20);
-                let attribute_hit_bonus = if weapon_info.attribute == WeaponAttribute::Might {
-                    attacker_attributes.might.bonus
-                } else {
-                    attacker_attributes.quickness.bonus
-                };
-                let skill_hit_bonus = skill_bonus(Skill::Melee, attacker_skills);
-                let weapon_hit_bonus = weapon_info.hit_bonus;
-                let mut status_hit_bonus = 0;
-                if let Some(hc) = hunger_clock.get(entity) {
-                    // Well-Fed grants +1
-                    if hc.state == HungerState::WellFed {
-                        status_hit_bonus += 1;
-                    }
-                }
-                let modified_hit_roll = natural_roll
-                    + attribute_hit_bonus
-                    + skill_hit_bonus
-                    + weapon_hit_bonus
-                    + status_hit_bonus;
+                let hit = melee_hit_bonus(
+                    &weapon_info,
+                    attacker_attributes,
+                    attacker_skills,
+                    hunger_clock.get(entity),
+                )
+                .with_roll(natural_roll);
+                let modified_hit_roll = hit.total;
                 //println!("Natural roll: {}", natural_roll);
                 //println!("Modified hit roll: {}", modified_hit_roll);
 
-                let mut armor_item_bonus_f = 0.0;
-                for (wielded, armor) in (&equipped_items, &wearables).join() {
-                    if wielded.owner == wants_melee.target {
-                        armor_item_bonus_f += armor.armor_class;
-                    }
-                }
-                let base_armor_class = natural
-                    .get(wants_melee.target)
-                    .map_or(10, |nat| nat.armor_class.unwrap_or(10));
-                let armor_quickness_bonus = target_attributes.quickness.bonus;
-                let armor_skill_bonus = skill_bonus(Skill::Defense, target_skills);
-                let armor_item_bonus = armor_item_bonus_f as i32;
-                let armor_class =
-                    base_armor_class + armor_quickness_bonus + armor_skill_bonus + armor_item_bonus;
+                let armor = armor_class(
+                    wants_melee.target,
+                    target_attributes,
+                    target_skills,
+                    &equipped_items,
+                    &wearables,
+                    &natural,
+                );
+                let armor_class = armor.total;
 
                 //println!("Armor class: {}", armor_class);
+                let mut damage_roll = None;
                 if natural_roll != 1 && (natural_roll == 20 || modified_hit_roll > armor_class) {
                     // Target hit! Until we support weapons, we're going with 1d4
                     let base_damage = crate::rng::roll_dice(
@@ -142,6 +200,13 @@ impl<'a> System<'a> for MeleeCombatSystem {
                         0,
                         base_damage + attr_damage_bonus + skill_damage_bonus + weapon_damage_bonus,
                     );
+                    damage_roll = Some(DamageRoll {
+                        base_roll: base_damage,
+                        attribute_bonus: attr_damage_bonus,
+                        skill_bonus: skill_damage_bonus,
+                        weapon_bonus: weapon_damage_bonus,
+                        total: damage,
+                    });
 
                     /*println!("Damage: {} + {}attr + {}skill + {}weapon = {}",
                         base_damage, attr_damage_bonus, skill_damage_bonus,
@@ -234,6 +299,25 @@ impl<'a> System<'a> for MeleeCombatSystem {
                         },
                     );
                 }
+
+                if event_bus::has_sinks() {
+                    let outcome = if damage_roll.is_some() {
+                        AttackOutcome::Hit
+                    } else if natural_roll == 1 {
+                        AttackOutcome::NaturalOne
+                    } else {
+                        AttackOutcome::Miss
+                    };
+                    event_bus::emit(&GameEvent::AttackResolved {
+                        kind: AttackKind::Melee,
+                        attacker: Participant::new(entity, &name.name),
+                        target: Participant::new(wants_melee.target, &target_name.name),
+                        outcome,
+                        hit,
+                        armor,
+                        damage: damage_roll,
+                    });
+                }
             }
         }
 
@@ -14,6 +14,7 @@ mod trigger_system;
 use trigger_system::TriggerSystem;
 mod melee_combat_system;
 use melee_combat_system::MeleeCombatSystem;
+pub use melee_combat_system::{armor_class, melee_hit_bonus, unarmed_attack};
 mod ranged_combat_system;
 use ranged_combat_system::RangedCombatSystem;
 mod inventory_system;
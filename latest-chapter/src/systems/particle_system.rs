@@ -2,7 +2,13 @@ use crate::{ParticleLifetime, Position, Renderable, Rltk};
 use rltk::RGB;
 use specs::prelude::*;
 
-pub fn update_particles(ecs: &mut World, ctx: &Rltk) {
+pub fn update_particles(ecs: &World, ctx: &Rltk) {
+    age_particles(ecs, ctx.frame_time_ms);
+}
+
+// Expired particles are only hidden here; deleting them depends on frame timing, which would
+// change which entity ids later spawns reuse. `cull_particles` removes them between turns.
+pub fn age_particles(ecs: &World, frame_time_ms: f32) {
     let mut dead_particles: Vec<Entity> = Vec::new();
     {
         // Age out particles
@@ -10,7 +16,7 @@ pub fn update_particles(ecs: &mut World, ctx: &Rltk) {
         let entities = ecs.entities();
         for (entity, particle) in (&entities, &mut particles).join() {
             if let Some(animation) = &mut particle.animation {
-                animation.timer += ctx.frame_time_ms;
+                animation.timer += frame_time_ms;
                 if animation.timer > animation.step_time
                     && animation.current_step < animation.path.len() - 2
                 {
@@ -23,14 +29,25 @@ pub fn update_particles(ecs: &mut World, ctx: &Rltk) {
                 }
             }
 
-            particle.lifetime_ms -= ctx.frame_time_ms;
+            particle.lifetime_ms -= frame_time_ms;
             if particle.lifetime_ms < 0.0 {
                 dead_particles.push(entity);
             }
         }
     }
+    let mut renderables = ecs.write_storage::<Renderable>();
     for dead in &dead_particles {
-        ecs.delete_entity(*dead).expect("Particle will not die");
+        renderables.remove(*dead);
+    }
+}
+
+pub fn cull_particles(ecs: &mut World) {
+    let particles: Vec<Entity> = (&ecs.entities(), &ecs.read_storage::<ParticleLifetime>())
+        .join()
+        .map(|(entity, _)| entity)
+        .collect();
+    for particle in &particles {
+        ecs.delete_entity(*particle).expect("Particle will not die");
     }
 }
 
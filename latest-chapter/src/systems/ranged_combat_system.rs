@@ -1,5 +1,7 @@
+use super::armor_class;
 use crate::{
     effects::{add_effect, EffectType, Targets},
+    event_bus::{self, AttackKind, AttackOutcome, DamageRoll, GameEvent, HitRoll, Participant},
     skill_bonus, Attributes, EquipmentSlot, Equipped, HungerClock, HungerState, Map, Name,
     NaturalAttackDefense, Pools, Position, Skill, Skills, WantsToShoot, Weapon, WeaponAttribute,
     Wearable,
@@ -140,22 +142,18 @@ impl<'a> System<'a> for RangedCombatSystem {
                 //println!("Natural roll: {}", natural_roll);
                 //println!("Modified hit roll: {}", modified_hit_roll);
 
-                let mut armor_item_bonus_f = 0.0;
-                for (wielded, armor) in (&equipped_items, &wearables).join() {
-                    if wielded.owner == wants_shoot.target {
-                        armor_item_bonus_f += armor.armor_class;
-                    }
-                }
-                let base_armor_class = natural
-                    .get(wants_shoot.target)
-                    .map_or(10, |nat| nat.armor_class.unwrap_or(10));
-                let armor_quickness_bonus = target_attributes.quickness.bonus;
-                let armor_skill_bonus = skill_bonus(Skill::Defense, target_skills);
-                let armor_item_bonus = armor_item_bonus_f as i32;
-                let armor_class =
-                    base_armor_class + armor_quickness_bonus + armor_skill_bonus + armor_item_bonus;
+                let armor = armor_class(
+                    wants_shoot.target,
+                    target_attributes,
+                    target_skills,
+                    &equipped_items,
+                    &wearables,
+                    &natural,
+                );
+                let armor_class = armor.total;
 
                 //println!("Armor class: {}", armor_class);
+                let mut damage_roll = None;
                 if natural_roll != 1 && (natural_roll == 20 || modified_hit_roll > armor_class) {
                     // Target hit! Until we support weapons, we're going with 1d4
                     let base_damage = crate::rng::roll_dice(
@@ -170,6 +168,13 @@ impl<'a> System<'a> for RangedCombatSystem {
                         0,
                         base_damage + attr_damage_bonus + skill_damage_bonus + weapon_damage_bonus,
                     );
+                    damage_roll = Some(DamageRoll {
+                        base_roll: base_damage,
+                        attribute_bonus: attr_damage_bonus,
+                        skill_bonus: skill_damage_bonus,
+                        weapon_bonus: weapon_damage_bonus,
+                        total: damage,
+                    });
 
                     /*println!("Damage: {} + {}attr + {}skill + {}weapon = {}",
                         base_damage, attr_damage_bonus, skill_damage_bonus,
@@ -255,6 +260,32 @@ impl<'a> System<'a> for RangedCombatSystem {
                         },
                     );
                 }
+
+                if event_bus::has_sinks() {
+                    let outcome = if damage_roll.is_some() {
+                        AttackOutcome::Hit
+                    } else if natural_roll == 1 {
+                        AttackOutcome::NaturalOne
+                    } else {
+                        AttackOutcome::Miss
+                    };
+                    event_bus::emit(&GameEvent::AttackResolved {
+                        kind: AttackKind::Ranged,
+                        attacker: Participant::new(entity, &name.name),
+                        target: Participant::new(wants_shoot.target, &target_name.name),
+                        outcome,
+                        hit: HitRoll {
+                            natural_roll,
+                            attribute_bonus: attribute_hit_bonus,
+                            skill_bonus: skill_hit_bonus,
+                            weapon_bonus: weapon_hit_bonus,
+                            status_bonus: status_hit_bonus,
+                            total: modified_hit_roll,
+                        },
+                        armor,
+                        damage: damage_roll,
+                    });
+                }
             }
         }
 
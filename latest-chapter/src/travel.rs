@@ -0,0 +1,648 @@
+use super::{
+    camera,
+    command::Command,
+    gamelog, player,
+    raws::{faction_reaction, Reaction, RAWS},
+    tile_cost, tile_walkable, Attributes, BlocksTile, Door, Faction, Hidden, HungerClock,
+    HungerState, Item, Map, Pools, Position, TeleportTo, TileType, Vendor,
+};
+use rltk::{Algorithm2D, BaseMap, Point, Rltk};
+use specs::prelude::*;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The map as the player knows it: only revealed floor can be walked, and anything seen standing
+// in the way is avoided. Doors and bystanders don't count, since walking into a door opens it
+// and walking into a bystander swaps places with them.
+struct KnownMap<'a> {
+    map: &'a Map,
+    blocked: HashSet<usize>,
+}
+
+impl<'a> KnownMap<'a> {
+    fn new(ecs: &World, map: &'a Map) -> Self {
+        let player_entity = *ecs.fetch::<Entity>();
+        let blocks = ecs.read_storage::<BlocksTile>();
+        let doors = ecs.read_storage::<Door>();
+        let attributes = ecs.read_storage::<Attributes>();
+        let factions = ecs.read_storage::<Faction>();
+        let vendors = ecs.read_storage::<Vendor>();
+        let raws = RAWS.lock().unwrap();
+        let mut blocked = HashSet::new();
+        for (idx, visible) in map.visible_tiles.iter().enumerate() {
+            if *visible {
+                crate::spatial::for_each_tile_content(idx, |entity| {
+                    let bystander = vendors.get(entity).is_none()
+                        && attributes.get(entity).is_some()
+                        && factions.get(entity).is_some_and(|faction| {
+                            faction_reaction(&faction.name, "Player", &raws) != Reaction::Attack
+                        });
+                    if entity != player_entity
+                        && blocks.get(entity).is_some()
+                        && doors.get(entity).is_none()
+                        && !bystander
+                    {
+                        blocked.insert(idx);
+                    }
+                });
+            }
+        }
+        Self { map, blocked }
+    }
+
+    fn is_exit_valid(&self, x: i32, y: i32) -> bool {
+        if x < 1 || x > self.map.width - 1 || y < 1 || y > self.map.height - 1 {
+            return false;
+        }
+        let idx = self.map.xy_idx(x, y);
+        self.map.revealed_tiles[idx]
+            && tile_walkable(self.map.tiles[idx])
+            && !self.blocked.contains(&idx)
+    }
+}
+
+impl BaseMap for KnownMap<'_> {
+    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        const DIAGONAL_COST: f32 = 1.5;
+        let mut exits = rltk::SmallVec::new();
+        let x = idx as i32 % self.map.width;
+        let y = idx as i32 / self.map.width;
+        for (dx, dy) in [
+            (-1, 0),
+            (1, 0),
+            (0, -1),
+            (0, 1),
+            (-1, -1),
+            (1, -1),
+            (-1, 1),
+            (1, 1),
+        ] {
+            if self.is_exit_valid(x + dx, y + dy) {
+                let dest = self.map.xy_idx(x + dx, y + dy);
+                let cost = tile_cost(self.map.tiles[dest]);
+                let cost = if dx != 0 && dy != 0 {
+                    cost * DIAGONAL_COST
+                } else {
+                    cost
+                };
+                exits.push((dest, cost));
+            }
+        }
+        exits
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.map.get_pathing_distance(idx1, idx2)
+    }
+}
+
+impl Algorithm2D for KnownMap<'_> {
+    fn dimensions(&self) -> Point {
+        self.map.dimensions()
+    }
+}
+
+const SEARCH_DEPTH: f32 = 1000.0;
+
+// Distances from every known tile to the nearest of `goals`.
+fn distances_to(map: &Map, known: &KnownMap, goals: &[usize]) -> rltk::DijkstraMap {
+    let mut dijkstra = rltk::DijkstraMap::new(map.width, map.height, goals, known, SEARCH_DEPTH);
+    // The flood fill leaves the goals themselves at whatever their neighbours reached
+    for goal in goals {
+        dijkstra.map[*goal] = 0.0;
+    }
+    dijkstra
+}
+
+// The tiles to walk through to get as close as possible to the nearest of `goals`, or None if
+// none can be reached. Goals that can't be stood on, like a vendor, are walked up to.
+fn path_to_nearest(ecs: &World, goals: &[usize]) -> Option<Vec<usize>> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = *ecs.fetch::<Point>();
+    let known = KnownMap::new(ecs, &map);
+    let dijkstra = distances_to(&map, &known, goals);
+    let mut idx = map.xy_idx(player_pos.x, player_pos.y);
+    if dijkstra.map[idx] >= SEARCH_DEPTH {
+        return None;
+    }
+    let mut path = Vec::new();
+    while let Some(next) = rltk::DijkstraMap::find_lowest_exit(&dijkstra, idx, &known)
+        .filter(|next| dijkstra.map[*next] < dijkstra.map[idx])
+    {
+        path.push(next);
+        idx = next;
+    }
+    Some(path)
+}
+
+// The tiles to walk through to reach `target`, not including where the player stands now.
+#[must_use]
+pub fn plan_path(ecs: &World, target: Point) -> Option<Vec<usize>> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = *ecs.fetch::<Point>();
+    if !map.in_bounds(target) || target == player_pos {
+        return None;
+    }
+    let target_idx = map.xy_idx(target.x, target.y);
+    if !map.revealed_tiles[target_idx] || !tile_walkable(map.tiles[target_idx]) {
+        return None;
+    }
+    let known = KnownMap::new(ecs, &map);
+    let path = rltk::a_star_search(map.xy_idx(player_pos.x, player_pos.y), target_idx, &known);
+    if path.success && path.steps.len() > 1 {
+        Some(path.steps[1..].to_vec())
+    } else {
+        None
+    }
+}
+
+// The map tile under the mouse, if it's over the part of the screen showing the map.
+#[must_use]
+pub fn mouse_tile(ecs: &World, ctx: &Rltk) -> Option<Point> {
+    let (min_x, max_x, min_y, max_y) = camera::get_screen_bounds(ecs, ctx);
+    let (mouse_x, mouse_y) = ctx.mouse_pos();
+    if mouse_x < 1 || mouse_x > max_x - min_x || mouse_y < 1 || mouse_y > max_y - min_y {
+        return None;
+    }
+    let tile = Point::new(mouse_x + min_x - 1, mouse_y + min_y - 1);
+    ecs.fetch::<Map>().in_bounds(tile).then_some(tile)
+}
+
+// Items the player can see right now.
+fn visible_items(ecs: &World) -> HashSet<Entity> {
+    let map = ecs.fetch::<Map>();
+    let items = ecs.read_storage::<Item>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let mut seen = HashSet::new();
+    for (idx, visible) in map.visible_tiles.iter().enumerate() {
+        if *visible {
+            crate::spatial::for_each_tile_content(idx, |entity| {
+                if items.get(entity).is_some() && hidden.get(entity).is_none() {
+                    seen.insert(entity);
+                }
+            });
+        }
+    }
+    seen
+}
+
+fn hidden_entities(ecs: &World) -> HashSet<Entity> {
+    let entities = ecs.entities();
+    let hidden = ecs.read_storage::<Hidden>();
+    (&entities, &hidden)
+        .join()
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+fn player_hit_points(ecs: &World) -> (i32, i32) {
+    ecs.read_storage::<Pools>()
+        .get(*ecs.fetch::<Entity>())
+        .map_or((0, 0), |pools| {
+            (pools.hit_points.current, pools.hit_points.max)
+        })
+}
+
+fn player_hunger(ecs: &World) -> Option<HungerState> {
+    ecs.read_storage::<HungerClock>()
+        .get(*ecs.fetch::<Entity>())
+        .map(|clock| clock.state)
+}
+
+static AUTO_PICKUP: AtomicBool = AtomicBool::new(false);
+static AUTO_STAIRS: AtomicBool = AtomicBool::new(false);
+
+// Whether exploring also walks over to items and picks them up.
+pub fn set_auto_pickup(enabled: bool) {
+    AUTO_PICKUP.store(enabled, Ordering::Relaxed);
+}
+
+// Whether travelling to the stairs also takes them on arrival.
+pub fn set_auto_stairs(enabled: bool) {
+    AUTO_STAIRS.store(enabled, Ordering::Relaxed);
+}
+
+// Where exploring heads next: the edge of what's been seen, and items worth picking up.
+fn explore_goals(ecs: &World, map: &Map, ignored: &HashSet<Entity>) -> Vec<usize> {
+    let mut goals = Vec::new();
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            let frontier = !map.revealed_tiles[idx]
+                && [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| {
+                    let next = map.xy_idx(x + dx, y + dy);
+                    map.revealed_tiles[next] && tile_walkable(map.tiles[next])
+                });
+            if frontier {
+                goals.push(idx);
+            }
+        }
+    }
+    if AUTO_PICKUP.load(Ordering::Relaxed) {
+        let positions = ecs.read_storage::<Position>();
+        for entity in visible_items(ecs) {
+            if !ignored.contains(&entity) {
+                if let Some(pos) = positions.get(entity) {
+                    goals.push(map.xy_idx(pos.x, pos.y));
+                }
+            }
+        }
+    }
+    goals
+}
+
+// A walk in progress, either along a path or exploring. The player takes one step per turn and
+// stops at anything worth their attention.
+pub struct Travel {
+    pub path: Vec<usize>,
+    exploring: bool,
+    hit_points: i32,
+    hunger: Option<HungerState>,
+    hidden: HashSet<Entity>,
+    seen: HashSet<Entity>,
+    picked_up: HashSet<Entity>,
+    last_position: Option<usize>,
+    attempts: u32,
+    arrival: Option<Command>,
+}
+
+impl Travel {
+    #[must_use]
+    pub fn new(ecs: &World, path: Vec<usize>) -> Self {
+        Self {
+            path,
+            exploring: false,
+            hit_points: player_hit_points(ecs).0,
+            hunger: player_hunger(ecs),
+            hidden: hidden_entities(ecs),
+            seen: visible_items(ecs),
+            picked_up: HashSet::new(),
+            last_position: None,
+            attempts: 0,
+            arrival: None,
+        }
+    }
+
+    #[must_use]
+    pub fn explore(ecs: &World) -> Self {
+        Self {
+            exploring: true,
+            ..Self::new(ecs, Vec::new())
+        }
+    }
+
+    // Sets off for the nearest stairs the player has seen.
+    #[must_use]
+    pub fn to_stairs(ecs: &World, down: bool) -> Option<Self> {
+        let (stairs, way, arrival) = if down {
+            (TileType::DownStairs, "down", Command::Descend)
+        } else {
+            (TileType::UpStairs, "up", Command::Ascend)
+        };
+        let goals: Vec<usize> = {
+            let map = ecs.fetch::<Map>();
+            (0..map.tiles.len())
+                .filter(|idx| map.revealed_tiles[*idx] && map.tiles[*idx] == stairs)
+                .collect()
+        };
+        if goals.is_empty() {
+            Self::stop(&format!("You haven't found a way {way} yet."));
+            return None;
+        }
+        let Some(path) = path_to_nearest(ecs, &goals) else {
+            Self::stop(&format!("You can't see a way to reach the stairs {way}."));
+            return None;
+        };
+        // Standing on the stairs already, the key just takes them.
+        let arrival = (path.is_empty() || AUTO_STAIRS.load(Ordering::Relaxed)).then_some(arrival);
+        Some(Self {
+            arrival,
+            ..Self::new(ecs, path)
+        })
+    }
+
+    // Sets off for the nearest vendor or town portal the player has seen.
+    #[must_use]
+    pub fn to_landmark(ecs: &World) -> Option<Self> {
+        let goals: Vec<usize> = {
+            let map = ecs.fetch::<Map>();
+            let entities = ecs.entities();
+            let positions = ecs.read_storage::<Position>();
+            let vendors = ecs.read_storage::<Vendor>();
+            let portals = ecs.read_storage::<TeleportTo>();
+            let hidden = ecs.read_storage::<Hidden>();
+            (&entities, &positions, !&hidden)
+                .join()
+                .filter(|(entity, _, ())| {
+                    vendors.get(*entity).is_some() || portals.get(*entity).is_some()
+                })
+                .map(|(_, pos, ())| map.xy_idx(pos.x, pos.y))
+                .filter(|idx| map.revealed_tiles[*idx])
+                .collect()
+        };
+        if goals.is_empty() {
+            Self::stop("You don't know of a vendor or portal here.");
+            return None;
+        }
+        let Some(path) = path_to_nearest(ecs, &goals) else {
+            Self::stop("You can't see a way to reach a vendor or portal.");
+            return None;
+        };
+        if path.is_empty() {
+            Self::stop("You are already there.");
+            return None;
+        }
+        Some(Self::new(ecs, path))
+    }
+
+    fn stop(reason: &str) -> Option<Command> {
+        gamelog::Logger::new()
+            .color(rltk::YELLOW)
+            .append(&reason)
+            .log();
+        None
+    }
+
+    // Reasons to stop that apply however the player is getting about.
+    fn interruption(&mut self, ecs: &World) -> Option<String> {
+        let (hit_points, max_hit_points) = player_hit_points(ecs);
+        if hit_points < self.hit_points {
+            return Some("You are hurt, and stop.".to_string());
+        }
+        self.hit_points = hit_points;
+        if self.exploring && hit_points * 2 < max_hit_points {
+            return Some("Your health is low, and you stop.".to_string());
+        }
+        if player::hostile_in_view(ecs) {
+            return Some("You see an enemy, and stop.".to_string());
+        }
+
+        let hunger = player_hunger(ecs);
+        if hunger != self.hunger {
+            self.hunger = hunger;
+            let feeling = match hunger {
+                Some(HungerState::WellFed) => "well fed",
+                Some(HungerState::Hungry) => "hungry",
+                Some(HungerState::Starving) => "starving",
+                _ => "no longer well fed",
+            };
+            return Some(format!("You are {feeling}, and stop."));
+        }
+
+        let still_hidden = hidden_entities(ecs);
+        let revealed: Vec<Entity> = self.hidden.difference(&still_hidden).copied().collect();
+        self.hidden = still_hidden;
+        let map = ecs.fetch::<Map>();
+        let positions = ecs.read_storage::<Position>();
+        for entity in revealed {
+            let in_view = positions
+                .get(entity)
+                .is_some_and(|pos| map.visible_tiles[map.xy_idx(pos.x, pos.y)]);
+            if in_view {
+                let name = crate::gui::get_item_display_name(ecs, entity);
+                return Some(format!("You discover {name}, and stop."));
+            }
+        }
+        None
+    }
+
+    // The next step along the path.
+    fn follow_path(&mut self, ecs: &World, player_idx: usize) -> Option<Point> {
+        let spotted: Vec<Entity> = visible_items(ecs)
+            .into_iter()
+            .filter(|entity| !self.seen.contains(entity))
+            .collect();
+        self.seen.extend(&spotted);
+        if let Some(entity) = spotted.first() {
+            let name = crate::gui::get_item_display_name(ecs, *entity);
+            Self::stop(&format!("You spot {name}, and stop."));
+            return None;
+        }
+
+        let map = ecs.fetch::<Map>();
+        if self.path.first() == Some(&player_idx) {
+            self.path.remove(0);
+        }
+        self.path
+            .first()
+            .map(|idx| Point::new(*idx as i32 % map.width, *idx as i32 / map.width))
+    }
+
+    // The next step downhill on a Dijkstra map flowing out from everything left to explore.
+    fn next_explore_step(&self, ecs: &World, player_idx: usize) -> Option<Point> {
+        let map = ecs.fetch::<Map>();
+        let goals = explore_goals(ecs, &map, &self.picked_up);
+        let known = KnownMap::new(ecs, &map);
+        let dijkstra = distances_to(&map, &known, &goals);
+        let here = dijkstra.map[player_idx];
+        let next = rltk::DijkstraMap::find_lowest_exit(&dijkstra, player_idx, &known)
+            .filter(|next| dijkstra.map[*next] < here);
+        if next.is_none() {
+            Self::stop("There is nothing left here you can reach to explore.");
+        }
+        next.map(|idx| Point::new(idx as i32 % map.width, idx as i32 / map.width))
+    }
+
+    // The next command to carry out, or None once the walk is over.
+    pub fn next_step(&mut self, ecs: &World) -> Option<Command> {
+        if let Some(reason) = self.interruption(ecs) {
+            return Self::stop(&reason);
+        }
+
+        let player_pos = *ecs.fetch::<Point>();
+        let player_idx = ecs.fetch::<Map>().xy_idx(player_pos.x, player_pos.y);
+        if self.exploring && AUTO_PICKUP.load(Ordering::Relaxed) {
+            let items = ecs.read_storage::<Item>();
+            let mut here = None;
+            crate::spatial::for_each_tile_content(player_idx, |entity| {
+                if items.get(entity).is_some() && !self.picked_up.contains(&entity) {
+                    here = Some(entity);
+                }
+            });
+            if let Some(item) = here {
+                // Only tried once, so an item that can't be picked up isn't tried forever
+                self.picked_up.insert(item);
+                self.last_position = None;
+                return Some(Command::PickUp);
+            }
+        }
+
+        // Arriving on the stairs takes them, if that was asked for
+        if self.arrival.is_some() && self.path.last().is_none_or(|last| *last == player_idx) {
+            return self.arrival.take();
+        }
+
+        let next = if self.exploring {
+            self.next_explore_step(ecs, player_idx)
+        } else {
+            self.follow_path(ecs, player_idx)
+        }?;
+
+        // Walking into a door opens it without moving, so one failed step is allowed. A second
+        // means something is in the way.
+        if self.last_position == Some(player_idx) {
+            self.attempts += 1;
+        } else {
+            self.attempts = 0;
+        }
+        self.last_position = Some(player_idx);
+        let (dx, dy) = (next.x - player_pos.x, next.y - player_pos.y);
+        if dx.abs() > 1 || dy.abs() > 1 || self.attempts > 1 {
+            return Self::stop("Something is in the way, and you stop.");
+        }
+        Some(Command::Move { dx, dy })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Travel;
+    use crate::command::{Cheat, Command};
+    use crate::gamelog;
+    use crate::headless::{test_lock, Simulation};
+    use crate::raws::{spawn_named_mob, SpawnType, RAWS};
+    use crate::{Map, Pools, Position, TileType, Vendor, Viewshed};
+    use rltk::Point;
+    use specs::prelude::*;
+
+    // Takes steps until the walk stops, returning the reason it gave.
+    fn walk(sim: &mut Simulation, travel: &mut Travel) -> String {
+        for _ in 0..500 {
+            let Some(command) = travel.next_step(&sim.state.ecs) else {
+                return gamelog::log_lines(1).pop().unwrap_or_default();
+            };
+            sim.step(command);
+        }
+        panic!("The walk never stopped");
+    }
+
+    fn set_hit_points(sim: &Simulation, hit_points: impl FnOnce(i32) -> i32) {
+        let player = *sim.state.ecs.fetch::<Entity>();
+        let mut pools = sim.state.ecs.write_storage::<Pools>();
+        let pools = pools.get_mut(player).unwrap();
+        pools.hit_points.current = hit_points(pools.hit_points.max);
+    }
+
+    // Exploring from the start of the game, which is under way before anything interrupts it.
+    fn start_exploring(sim: &mut Simulation) -> Travel {
+        let mut travel = Travel::explore(&sim.state.ecs);
+        let command = travel
+            .next_step(&sim.state.ecs)
+            .expect("Exploring didn't start");
+        sim.step(command);
+        travel
+    }
+
+    #[test]
+    fn exploring_stops_when_an_enemy_comes_into_view() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(16);
+        let mut travel = start_exploring(&mut sim);
+
+        // Somewhere the player can see, but not so close it attacks straight away
+        let player_pos = *sim.state.ecs.fetch::<Point>();
+        let spot = sim
+            .state
+            .ecs
+            .read_storage::<Viewshed>()
+            .get(*sim.state.ecs.fetch::<Entity>())
+            .unwrap()
+            .visible_tiles
+            .iter()
+            .copied()
+            .find(|tile| {
+                let map = sim.state.ecs.fetch::<Map>();
+                let idx = map.xy_idx(tile.x, tile.y);
+                rltk::DistanceAlg::Pythagoras.distance2d(*tile, player_pos) > 3.0
+                    && crate::map::tile_walkable(map.tiles[idx])
+                    && !crate::spatial::is_blocked(idx)
+            })
+            .expect("Nowhere in view to put a rat");
+        spawn_named_mob(
+            &RAWS.lock().unwrap(),
+            &mut sim.state.ecs,
+            "Rat",
+            SpawnType::AtPosition {
+                x: spot.x,
+                y: spot.y,
+            },
+        );
+        sim.state.run_systems();
+
+        assert_eq!(walk(&mut sim, &mut travel), "You see an enemy, and stop.");
+    }
+
+    #[test]
+    fn exploring_stops_when_hurt() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(16);
+        let mut travel = start_exploring(&mut sim);
+        set_hit_points(&sim, |max| max - 1);
+        assert!(travel.next_step(&sim.state.ecs).is_none());
+        assert_eq!(gamelog::log_lines(1), ["You are hurt, and stop."]);
+    }
+
+    #[test]
+    fn exploring_stops_when_health_is_low() {
+        let _lock = test_lock();
+        let sim = Simulation::new(16);
+        set_hit_points(&sim, |max| max / 2 - 1);
+        let mut travel = Travel::explore(&sim.state.ecs);
+        assert!(travel.next_step(&sim.state.ecs).is_none());
+        assert_eq!(gamelog::log_lines(1), ["Your health is low, and you stop."]);
+    }
+
+    #[test]
+    fn travelling_to_a_landmark_walks_up_to_it() {
+        let _lock = test_lock();
+        let mut sim = Simulation::new(42);
+        sim.step(Command::Cheat {
+            cheat: Cheat::Reveal,
+        });
+        let mut travel = Travel::to_landmark(&sim.state.ecs).expect("No landmark to go to");
+        let goal = *travel.path.last().unwrap();
+        walk(&mut sim, &mut travel);
+
+        let ecs = &sim.state.ecs;
+        let player_pos = *ecs.fetch::<Point>();
+        let map = ecs.fetch::<Map>();
+        assert_eq!(map.xy_idx(player_pos.x, player_pos.y), goal);
+        let beside_a_vendor = (
+            &ecs.read_storage::<Position>(),
+            &ecs.read_storage::<Vendor>(),
+        )
+            .join()
+            .any(|(pos, _)| {
+                rltk::DistanceAlg::Chebyshev.distance2d(player_pos, Point::new(pos.x, pos.y)) <= 1.0
+            });
+        assert!(beside_a_vendor);
+    }
+
+    // Without auto-stairs, '>' on the stairs still goes down them.
+    #[test]
+    fn travelling_to_the_stairs_underfoot_takes_them() {
+        let _lock = test_lock();
+        let sim = Simulation::new(16);
+        let ecs = &sim.state.ecs;
+        let stairs = {
+            let mut map = ecs.fetch_mut::<Map>();
+            let idx = map
+                .tiles
+                .iter()
+                .position(|tile| *tile == TileType::DownStairs)
+                .unwrap();
+            map.revealed_tiles[idx] = true;
+            Point::new(idx as i32 % map.width, idx as i32 / map.width)
+        };
+        let player = *ecs.fetch::<Entity>();
+        *ecs.write_storage::<Position>().get_mut(player).unwrap() = Position {
+            x: stairs.x,
+            y: stairs.y,
+        };
+        *ecs.fetch_mut::<Point>() = stairs;
+
+        let mut travel = Travel::to_stairs(ecs, true).unwrap();
+        assert!(travel.path.is_empty());
+        assert_eq!(travel.next_step(ecs), Some(Command::Descend));
+    }
+}